@@ -50,7 +50,9 @@ async fn main() -> anyhow::Result<()> {
     match (map.get("state"), map.get("code")) {
         (Some(state), Some(code)) => {
             // Finish the builder with `get_user_token`
-            let token = builder.get_user_token(&reqwest, state, code).await?;
+            let token = builder
+                .get_user_token(&reqwest, state, code.as_ref())
+                .await?;
             println!("Got token: {:?}", token);
         }
         _ => match (map.get("error"), map.get("error_description")) {
@@ -0,0 +1,52 @@
+//! Example of how to create a app access token without an async runtime
+//!
+//! This uses the `blocking` feature, with a blocking http client sending the requests.
+use twitch_oauth2::{blocking::SyncClient, AppAccessToken, TwitchToken};
+
+fn main() -> anyhow::Result<()> {
+    let _ = dotenv::dotenv(); // Eat error
+    let mut args = std::env::args().skip(1);
+
+    // Setup the blocking http client to use with the library.
+    let reqwest = reqwest::blocking::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let client = SyncClient::new(|request| send(&reqwest, request));
+
+    // Grab the client id, convert to a `ClientId` with the `new` method.
+    let client_id = get_env_or_arg("TWITCH_CLIENT_ID", &mut args)
+        .map(twitch_oauth2::ClientId::new)
+        .expect("Please set env: TWITCH_CLIENT_ID or pass client id as an argument");
+
+    // Grab the client secret, convert to a `ClientSecret` with the `new` method.
+    let client_secret = get_env_or_arg("TWITCH_CLIENT_SECRET", &mut args)
+        .map(twitch_oauth2::ClientSecret::new)
+        .expect("Please set env: TWITCH_CLIENT_SECRET or pass client secret as an argument");
+
+    // Get the token, blocking until it's done.
+    let token =
+        AppAccessToken::get_app_access_token_blocking(&client, client_id, client_secret, vec![])?;
+
+    println!("{:?}", token);
+    dbg!(token.is_elapsed());
+    Ok(())
+}
+
+/// Send a `http::Request` with the blocking reqwest client and translate the response into a `http::Response`
+fn send(
+    client: &reqwest::blocking::Client,
+    request: http::Request<Vec<u8>>,
+) -> Result<http::Response<Vec<u8>>, reqwest::Error> {
+    let response = client.execute(request.try_into()?)?;
+    let mut result = http::Response::builder().status(response.status());
+    for (name, value) in response.headers() {
+        result = result.header(name, value);
+    }
+    Ok(result
+        .body(response.bytes()?.to_vec())
+        .expect("response from reqwest should be valid"))
+}
+
+fn get_env_or_arg(env: &str, args: &mut impl Iterator<Item = String>) -> Option<String> {
+    std::env::var(env).ok().or_else(|| args.next())
+}
@@ -0,0 +1,171 @@
+//! Blocking versions of the token functions, for use without an async runtime.
+//!
+//! The functions wrap the async functions with [`block_on`], a tiny executor that blocks the current thread.
+//! The [`Client`] used must not need a runtime itself. Use [`SyncClient`] to send requests with any blocking http library.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use twitch_oauth2::{blocking::SyncClient, AppAccessToken, TwitchToken};
+//!
+//! # fn send(_: http::Request<Vec<u8>>) -> Result<http::Response<Vec<u8>>, std::io::Error> { todo!() }
+//! // `send` is a function that sends a request with a blocking http client, like `ureq` or `reqwest::blocking`.
+//! let client = SyncClient::new(send);
+//! let token = AppAccessToken::get_app_access_token_blocking(
+//!     &client,
+//!     "myclientid".into(),
+//!     "myclientsecret".into(),
+//!     vec![],
+//! )?;
+//! println!("token expires in {:?}", token.expires_in());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use crate::client::{BoxedFuture, Client};
+use crate::tokens::errors::{AppAccessTokenError, ValidationError};
+use crate::types::{AccessToken, ClientId, ClientSecret, RefreshToken};
+use crate::{AppAccessToken, Scope, UserToken};
+
+/// Run a future to completion on the current thread, blocking until it's done.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(std::thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) { self.0.unpark() }
+    }
+
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+/// A [`Client`] that sends requests with a blocking function.
+///
+/// The function is called when the request is made, so it blocks the current thread.
+#[derive(Debug, Clone)]
+pub struct SyncClient<F> {
+    send: F,
+}
+
+impl<F> SyncClient<F> {
+    /// Create a [`SyncClient`] sending requests with `send`
+    pub fn new(send: F) -> Self { SyncClient { send } }
+}
+
+impl<F, E> Client for SyncClient<F>
+where
+    F: Fn(http::Request<Vec<u8>>) -> Result<http::Response<Vec<u8>>, E> + Send + Sync,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    type Error = E;
+
+    fn req(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> BoxedFuture<'_, Result<http::Response<Vec<u8>>, Self::Error>> {
+        let response = (self.send)(request);
+        Box::pin(async move { response })
+    }
+}
+
+impl UserToken {
+    /// Blocking version of [`UserToken::from_existing`]
+    pub fn from_existing_blocking<C>(
+        http_client: &C,
+        access_token: AccessToken,
+        refresh_token: impl Into<Option<RefreshToken>>,
+        client_secret: impl Into<Option<ClientSecret>>,
+    ) -> Result<UserToken, ValidationError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        block_on(UserToken::from_existing(
+            http_client,
+            access_token,
+            refresh_token,
+            client_secret,
+        ))
+    }
+}
+
+impl AppAccessToken {
+    /// Blocking version of [`AppAccessToken::get_app_access_token`]
+    pub fn get_app_access_token_blocking<C>(
+        http_client: &C,
+        client_id: ClientId,
+        client_secret: ClientSecret,
+        scopes: Vec<Scope>,
+    ) -> Result<AppAccessToken, AppAccessTokenError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        block_on(AppAccessToken::get_app_access_token(
+            http_client,
+            client_id,
+            client_secret,
+            scopes,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+    use crate::TwitchToken;
+
+    #[test]
+    fn block_on_wakes() {
+        let (sender, receiver) = std::sync::mpsc::channel::<Waker>();
+        let handle = std::thread::spawn(move || receiver.recv().unwrap().wake());
+        let mut sender = Some(sender);
+        let output = block_on(std::future::poll_fn(|cx| match sender.take() {
+            Some(sender) => {
+                sender.send(cx.waker().clone()).unwrap();
+                Poll::Pending
+            }
+            None => Poll::Ready(42),
+        }));
+        assert_eq!(output, 42);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn from_existing_blocking() {
+        let mock = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response("random_client", "user", "1234", &[], 3600),
+        );
+        let client = SyncClient::new(|request| block_on(mock.req(request)));
+        let token = UserToken::from_existing_blocking(&client, "token".into(), None, None).unwrap();
+        assert_eq!(token.user_id.as_str(), "1234");
+    }
+
+    #[test]
+    fn get_app_access_token_blocking() {
+        let mock = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("token", None, &[], 3600),
+        );
+        let client = SyncClient::new(|request| block_on(mock.req(request)));
+        let token = AppAccessToken::get_app_access_token_blocking(
+            &client,
+            "random_client".into(),
+            "random_secret".into(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(token.token().secret(), "token");
+    }
+}
@@ -1,4 +1,16 @@
 //! Provides different http clients
+//!
+//! With the `reqwest` feature, `reqwest::Client` implements [`Client`], so a single client can be shared and passed to
+//! functions like [`UserToken::from_token`](crate::UserToken::from_token). With the `surf` feature, `surf::Client` is supported in the same way,
+//! and with the `isahc` feature, `isahc::HttpClient`.
+//!
+//! For the browser, the `wasm` feature provides `WasmClient`, which uses the fetch api.
+//!
+//! # Timeouts
+//!
+//! This crate does not add any timeouts to requests, the defaults of the client are used, which for `reqwest` and `isahc` means no timeout.
+//! A hung connection to twitch can then stall a request indefinitely. Use a client with a timeout to avoid this, for example
+//! with `reqwest_http_client_with_timeout`, `surf_http_client_with_timeout` (with the `surf_client_curl` feature) or `isahc_http_client_with_timeout`.
 
 // This module is heavily inspired (read: copied) by twitch_api2::client.
 
@@ -10,9 +22,11 @@ pub static TWITCH_OAUTH2_USER_AGENT: &str =
     concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
 /// A boxed future, mimics `futures::future::BoxFuture`
-type BoxedFuture<'a, T> = std::pin::Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+pub(crate) type BoxedFuture<'a, T> = std::pin::Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
 /// A client that can do OAUTH2 requests
+///
+/// Clients are always passed by reference, so they don't need to be `Copy` or `Clone`, and one client can be used for multiple requests.
 pub trait Client: Sync + Send {
     /// Error returned by the client
     type Error: Error + Send + Sync + 'static;
@@ -28,7 +42,6 @@ pub trait Client: Sync + Send {
 #[error("this client does not do anything, only used for documentation test that only checks code integrity")]
 pub struct DummyClient;
 
-#[cfg(feature = "reqwest")]
 impl Client for DummyClient {
     type Error = DummyClient;
 
@@ -74,6 +87,19 @@ impl Client for ReqwestClient {
     }
 }
 
+/// Create a [reqwest](https://crates.io/crates/reqwest) client that doesn't follow redirects and fails requests taking longer than `timeout`.
+///
+/// A request that timed out fails with a [`reqwest::Error`] where [`is_timeout`](reqwest::Error::is_timeout) is `true`.
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+pub fn reqwest_http_client_with_timeout(
+    timeout: std::time::Duration,
+) -> Result<ReqwestClient, reqwest::Error> {
+    ReqwestClient::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(timeout)
+        .build()
+}
+
 #[cfg(feature = "surf")]
 use surf::Client as SurfClient;
 
@@ -83,6 +109,8 @@ use surf::Client as SurfClient;
 pub enum SurfError {
     /// surf failed to do the request: {0}
     Surf(surf::Error),
+    /// request timed out
+    Timeout,
     /// could not construct header value
     InvalidHeaderValue(#[from] http::header::InvalidHeaderValue),
     /// could not construct header name
@@ -145,7 +173,14 @@ impl Client for SurfClient {
         let client = self.clone();
         Box::pin(async move {
             // Send the request and translate the response into a `http::Response`
-            let mut response = client.send(req).await.map_err(SurfError::Surf)?;
+            let mut response = client.send(req).await.map_err(|e| {
+                // surf reports its timeout as an error with `408 Request Timeout`, responses from the server are never errors.
+                if e.status() == surf::StatusCode::RequestTimeout {
+                    SurfError::Timeout
+                } else {
+                    SurfError::Surf(e)
+                }
+            })?;
             let mut result = http::Response::builder().status(
                 http::StatusCode::from_u16(response.status().into())
                     .expect("http_types::StatusCode only contains valid status codes"),
@@ -161,7 +196,11 @@ impl Client for SurfClient {
                 })
                 .collect::<Result<_, SurfError>>()?;
 
-            let _ = std::mem::replace(&mut result.headers_mut(), Some(&mut response_headers));
+            let headers = result
+                .headers_mut()
+                // This should not fail, we just created the response.
+                .expect("expected to get headers mut when building response");
+            std::mem::swap(headers, &mut response_headers);
             let result = if let Some(v) = response.version() {
                 result.version(match v {
                     surf::http::Version::Http0_9 => http::Version::HTTP_09,
@@ -181,3 +220,341 @@ impl Client for SurfClient {
         })
     }
 }
+
+/// Create a [surf](https://crates.io/crates/surf) client that fails requests taking longer than `timeout` with [`SurfError::Timeout`].
+#[cfg(feature = "surf_client_curl")]
+pub fn surf_http_client_with_timeout(
+    timeout: std::time::Duration,
+) -> Result<SurfClient, SurfError> {
+    surf::Config::new()
+        .set_timeout(Some(timeout))
+        .try_into()
+        .map_err(SurfError::Surf)
+}
+
+#[cfg(feature = "isahc")]
+use isahc::HttpClient as IsahcClient;
+
+/// Possible errors from [`Client::req()`] when using the [isahc](https://crates.io/crates/isahc) client
+#[cfg(feature = "isahc")]
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum IsahcError {
+    /// isahc failed to do the request: {0}
+    Isahc(#[from] isahc::Error),
+    /// request timed out
+    Timeout,
+    /// could not construct request
+    Request(#[from] isahc::http::Error),
+    /// could not read response body
+    Body(#[from] std::io::Error),
+    /// could not construct response
+    Response(#[from] http::Error),
+}
+
+#[cfg(feature = "isahc")]
+impl Client for IsahcClient {
+    type Error = IsahcError;
+
+    fn req(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> BoxedFuture<'_, Result<http::Response<Vec<u8>>, Self::Error>> {
+        // isahc uses a different version of `http`, so we translate the request piece by piece.
+        let (parts, body) = request.into_parts();
+        let mut req = isahc::http::Request::builder()
+            .method(parts.method.as_str())
+            .uri(parts.uri.to_string());
+        for (name, value) in parts.headers.iter() {
+            req = req.header(name.as_str(), value.as_bytes());
+        }
+        let req = match req.body(body) {
+            Ok(req) => req,
+            Err(err) => return Box::pin(async move { Err(err.into()) }),
+        };
+
+        // We need to "call" the send outside the async closure to not capture self.
+        let fut = self.send_async(req);
+        Box::pin(async move {
+            use isahc::AsyncReadResponseExt;
+
+            // Send the request and translate the response into a `http::Response`
+            let mut response = fut.await.map_err(|e| match e.kind() {
+                isahc::error::ErrorKind::Timeout => IsahcError::Timeout,
+                _ => IsahcError::Isahc(e),
+            })?;
+            let mut result = http::Response::builder().status(response.status().as_u16());
+            for (name, value) in response.headers().iter() {
+                result = result.header(name.as_str(), value.as_bytes());
+            }
+            let body = response.bytes().await?;
+            Ok(result.body(body)?)
+        })
+    }
+}
+
+/// Create an [isahc](https://crates.io/crates/isahc) client that fails requests taking longer than `timeout` with [`IsahcError::Timeout`].
+#[cfg(feature = "isahc")]
+pub fn isahc_http_client_with_timeout(
+    timeout: std::time::Duration,
+) -> Result<IsahcClient, IsahcError> {
+    use isahc::config::Configurable;
+
+    IsahcClient::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(IsahcError::Isahc)
+}
+
+/// A [`Client`] for `wasm32-unknown-unknown` that uses the [fetch api](https://developer.mozilla.org/en-US/docs/Web/API/Fetch_API) of the browser through [gloo-net](https://crates.io/crates/gloo-net).
+///
+/// Useful for validating a token from the [implicit flow](crate::tokens::ImplicitUserTokenBuilder) directly in the browser.
+///
+/// # Notes
+///
+/// The browser enforces [CORS](https://developer.mozilla.org/en-US/docs/Web/HTTP/CORS), requests blocked by it fail with [`WasmClientError::Fetch`].
+/// Futures returned by this client must be polled on the thread that created them, which is always the case on `wasm32-unknown-unknown`.
+#[cfg(feature = "wasm")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WasmClient;
+
+/// Possible errors from [`Client::req()`] when using the [`WasmClient`]
+#[cfg(feature = "wasm")]
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum WasmClientError {
+    /// fetch failed, the request was possibly blocked by CORS or the network is unavailable: {0}
+    Fetch(String),
+    /// gloo-net failed to do the request: {0}
+    Gloo(#[from] gloo_net::Error),
+    /// header {0} is not valid utf-8
+    InvalidHeader(http::header::HeaderName),
+    /// could not construct response
+    Response(#[from] http::Error),
+}
+
+#[cfg(feature = "wasm")]
+impl Client for WasmClient {
+    type Error = WasmClientError;
+
+    fn req(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> BoxedFuture<'_, Result<http::Response<Vec<u8>>, Self::Error>> {
+        // The fetch api is not `Send`, but wasm32-unknown-unknown is single threaded, so wrapping it is fine.
+        Box::pin(send_wrapper::SendWrapper::new(async move {
+            let (parts, body) = request.into_parts();
+            let mut req =
+                gloo_net::http::RequestBuilder::new(&parts.uri.to_string()).method(parts.method);
+            for (name, value) in parts.headers.iter() {
+                let value = value
+                    .to_str()
+                    .map_err(|_| WasmClientError::InvalidHeader(name.clone()))?;
+                req = req.header(name.as_str(), value);
+            }
+            // fetch does not allow a body on `GET` requests, even an empty one
+            let req = if body.is_empty() {
+                req.build()?
+            } else {
+                req.body(js_sys::Uint8Array::from(body.as_slice()))?
+            };
+
+            let response = req.send().await.map_err(|e| match e {
+                // fetch rejects with a `TypeError` on network errors, including CORS failures
+                gloo_net::Error::JsError(e) if e.name == "TypeError" => {
+                    WasmClientError::Fetch(e.message)
+                }
+                e => e.into(),
+            })?;
+            let mut result = http::Response::builder().status(response.status());
+            for (name, value) in response.headers().entries() {
+                result = result.header(name, value);
+            }
+            Ok(result.body(response.binary().await?)?)
+        }))
+    }
+}
+
+/// Possible errors from [`Client::req()`] when using the [`MockClient`]
+#[cfg(any(test, feature = "mock_client"))]
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum MockClientError {
+    /// no response defined for {0} {1}
+    NoResponse(http::Method, String),
+    /// uri could not be translated into an url.
+    UrlError(#[from] url::ParseError),
+}
+
+/// A [`Client`] that returns canned responses, for testing code that uses this crate without talking to twitch.
+///
+/// Requests are matched on method and url, ignoring the query.
+///
+/// # Examples
+///
+/// ```rust
+/// use twitch_oauth2::{client::MockClient, AccessToken, Scope, UserToken};
+///
+/// let client = MockClient::new().with_response(
+///     http::Method::GET,
+///     &twitch_oauth2::VALIDATE_URL,
+///     MockClient::validate_response("clientid", "justintv", "1337", &[Scope::ChatRead], 3600),
+/// );
+/// # async {
+/// let token = UserToken::from_token(&client, AccessToken::from("token")).await?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())};
+/// ```
+#[cfg(any(test, feature = "mock_client"))]
+#[derive(Debug, Clone, Default)]
+pub struct MockClient {
+    responses: Vec<MockResponse>,
+}
+
+#[cfg(any(test, feature = "mock_client"))]
+#[derive(Debug, Clone)]
+struct MockResponse {
+    method: http::Method,
+    url: url::Url,
+    status: http::StatusCode,
+    headers: http::HeaderMap,
+    body: Vec<u8>,
+}
+
+#[cfg(any(test, feature = "mock_client"))]
+impl MockClient {
+    /// Create a [`MockClient`] without any responses
+    pub fn new() -> Self { Self::default() }
+
+    /// Respond with `response` to requests with the given method and url.
+    ///
+    /// If multiple responses match a request, the first one added is used.
+    pub fn with_response(
+        mut self,
+        method: http::Method,
+        url: &url::Url,
+        response: http::Response<Vec<u8>>,
+    ) -> Self {
+        let (parts, body) = response.into_parts();
+        self.responses.push(MockResponse {
+            method,
+            url: strip_query(url.clone()),
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        });
+        self
+    }
+
+    /// Create a response from `https://id.twitch.tv/oauth2/validate` for a user token
+    pub fn validate_response(
+        client_id: &str,
+        login: &str,
+        user_id: &str,
+        scopes: &[crate::Scope],
+        expires_in: u64,
+    ) -> http::Response<Vec<u8>> {
+        json_response(
+            http::StatusCode::OK,
+            serde_json::json!({
+                "client_id": client_id,
+                "login": login,
+                "user_id": user_id,
+                "scopes": scopes,
+                "expires_in": expires_in,
+            }),
+        )
+    }
+
+    /// Create a response from `https://id.twitch.tv/oauth2/validate` for an app access token, which has no `login` or `user_id`
+    pub fn validate_app_response(
+        client_id: &str,
+        scopes: &[crate::Scope],
+        expires_in: u64,
+    ) -> http::Response<Vec<u8>> {
+        json_response(
+            http::StatusCode::OK,
+            serde_json::json!({
+                "client_id": client_id,
+                "scopes": scopes,
+                "expires_in": expires_in,
+            }),
+        )
+    }
+
+    /// Create a response from `https://id.twitch.tv/oauth2/token`, as returned when getting or refreshing a token
+    pub fn token_response(
+        access_token: &str,
+        refresh_token: Option<&str>,
+        scopes: &[crate::Scope],
+        expires_in: u64,
+    ) -> http::Response<Vec<u8>> {
+        json_response(
+            http::StatusCode::OK,
+            serde_json::json!({
+                "access_token": access_token,
+                "refresh_token": refresh_token,
+                "scope": scopes,
+                "expires_in": expires_in,
+                "token_type": "bearer",
+            }),
+        )
+    }
+
+    /// Create an error response from twitch
+    pub fn error_response(status: http::StatusCode, message: &str) -> http::Response<Vec<u8>> {
+        json_response(
+            status,
+            serde_json::json!({
+                "status": status.as_u16(),
+                "message": message,
+            }),
+        )
+    }
+
+    fn respond(
+        &self,
+        request: &http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, MockClientError> {
+        let url = strip_query(url::Url::parse(&request.uri().to_string())?);
+        let mock = self
+            .responses
+            .iter()
+            .find(|mock| mock.method == request.method() && mock.url == url)
+            .ok_or_else(|| {
+                MockClientError::NoResponse(request.method().clone(), url.to_string())
+            })?;
+        let mut response = http::Response::builder().status(mock.status);
+        *response
+            .headers_mut()
+            .expect("expected to get headers mut when building response") = mock.headers.clone();
+        Ok(response
+            .body(mock.body.clone())
+            .expect("mock response should be valid"))
+    }
+}
+
+#[cfg(any(test, feature = "mock_client"))]
+fn strip_query(mut url: url::Url) -> url::Url {
+    url.set_query(None);
+    url.set_fragment(None);
+    url
+}
+
+#[cfg(any(test, feature = "mock_client"))]
+fn json_response(status: http::StatusCode, body: serde_json::Value) -> http::Response<Vec<u8>> {
+    http::Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string().into_bytes())
+        .expect("mock response should be valid")
+}
+
+#[cfg(any(test, feature = "mock_client"))]
+impl Client for MockClient {
+    type Error = MockClientError;
+
+    fn req(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> BoxedFuture<'_, Result<http::Response<Vec<u8>>, Self::Error>> {
+        let response = self.respond(&request);
+        Box::pin(async move { response })
+    }
+}
@@ -2,7 +2,7 @@
 
 use serde_derive::{Deserialize, Serialize};
 
-use crate::{AccessToken, RequestParseError};
+use crate::{tokens::errors::IdTokenError, AccessToken, RequestParseError};
 use std::time::Duration;
 /// Twitch's representation of the oauth flow.
 ///
@@ -24,15 +24,67 @@ pub struct TwitchTokenResponse {
     #[serde(rename = "scope", deserialize_with = "scope::deserialize")]
     #[serde(default)]
     pub scopes: Option<Vec<crate::Scope>>,
+    /// Type of the token, should be `bearer`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    /// The [OpenID Connect](https://dev.twitch.tv/docs/authentication/getting-tokens-oidc/) ID token, a JWT.
+    ///
+    /// Only present if the [`openid`](crate::Scope::OpenId) scope was requested, see [`UserTokenBuilder::openid`](crate::tokens::UserTokenBuilder::openid).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
 }
 
 impl TwitchTokenResponse {
     /// Create a [TwitchTokenResponse] from a [http::Response]
+    ///
+    /// Fails with [`RequestParseError::UnexpectedTokenType`] if the `token_type` is not `bearer`.
     pub fn from_response<B: AsRef<[u8]>>(
         response: &http::Response<B>,
     ) -> Result<TwitchTokenResponse, RequestParseError> {
+        let response: TwitchTokenResponse = crate::parse_response(response)?;
+        match response.token_type {
+            Some(ref found) if !found.eq_ignore_ascii_case("bearer") => {
+                Err(RequestParseError::UnexpectedTokenType {
+                    found: found.clone(),
+                })
+            }
+            _ => Ok(response),
+        }
+    }
+}
+
+/// Twitch's representation of the device code flow.
+///
+/// Retrieve with
+///
+/// * [`DeviceUserTokenBuilder::get_device_code_request`](crate::tokens::DeviceUserTokenBuilder::get_device_code_request)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeviceCodeResponse {
+    /// The identifier for the device, used when exchanging for a token
+    pub device_code: String,
+    /// Time (in seconds) until the device code expires
+    pub expires_in: u64,
+    /// Time (in seconds) to wait between polling for the token
+    pub interval: u64,
+    /// Code the user needs to enter on [`verification_uri`](DeviceCodeResponse::verification_uri)
+    pub user_code: String,
+    /// The URL the user needs to visit to authorize the device
+    pub verification_uri: String,
+}
+
+impl DeviceCodeResponse {
+    /// Create a [DeviceCodeResponse] from a [http::Response]
+    pub fn from_response<B: AsRef<[u8]>>(
+        response: &http::Response<B>,
+    ) -> Result<DeviceCodeResponse, RequestParseError> {
         crate::parse_response(response)
     }
+
+    /// Get the expires in from this response
+    pub fn expires_in(&self) -> Duration { Duration::from_secs(self.expires_in) }
+
+    /// Get the polling interval from this response
+    pub fn interval(&self) -> Duration { Duration::from_secs(self.interval) }
 }
 
 /// Twitch's representation of the oauth flow for errors
@@ -61,6 +113,70 @@ impl std::fmt::Display for TwitchTokenErrorResponse {
     }
 }
 
+impl TwitchTokenErrorResponse {
+    /// Get the [kind](TwitchErrorKind) of this error, parsed from the [`error`](TwitchTokenErrorResponse::error) and [`message`](TwitchTokenErrorResponse::message).
+    ///
+    /// ```rust
+    /// use twitch_oauth2::id::{TwitchErrorKind, TwitchTokenErrorResponse};
+    /// let error: TwitchTokenErrorResponse =
+    ///     serde_json::from_str(r#"{"status":400,"message":"Invalid refresh token"}"#)?;
+    /// assert_eq!(error.kind(), TwitchErrorKind::InvalidGrant);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn kind(&self) -> TwitchErrorKind {
+        let normalize = |s: &str| s.to_ascii_lowercase().replace('_', " ");
+        let texts = [
+            self.error.as_deref().map(normalize),
+            Some(normalize(&self.message)),
+        ];
+        let has = |needle: &str| texts.iter().flatten().any(|text| text.contains(needle));
+        if has("authorization pending") {
+            TwitchErrorKind::AuthorizationPending
+        } else if has("slow down") {
+            TwitchErrorKind::SlowDown
+        } else if has("access denied") {
+            TwitchErrorKind::AccessDenied
+        } else if has("invalid client") {
+            TwitchErrorKind::InvalidClient
+        } else if has("redirect mismatch") || has("redirect uri") {
+            TwitchErrorKind::RedirectMismatch
+        } else if has("invalid scope") {
+            TwitchErrorKind::InvalidScope
+        } else if has("invalid grant")
+            || has("invalid authorization code")
+            || has("invalid refresh token")
+            || has("invalid device code")
+            || has("expired token")
+        {
+            TwitchErrorKind::InvalidGrant
+        } else {
+            TwitchErrorKind::Unknown(self.message.clone())
+        }
+    }
+}
+
+/// Kind of error returned by twitch, see [`TwitchTokenErrorResponse::kind`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TwitchErrorKind {
+    /// The client id or client secret is invalid
+    InvalidClient,
+    /// The authorization code, refresh token or device code is invalid or expired
+    InvalidGrant,
+    /// The redirect uri does not match the redirect uri registered for the client
+    RedirectMismatch,
+    /// A requested scope is invalid
+    InvalidScope,
+    /// The user has not yet authorized the device in the device code flow
+    AuthorizationPending,
+    /// The device code flow is polled too often, the interval should be increased
+    SlowDown,
+    /// The user denied the authorization request in the device code flow
+    AccessDenied,
+    /// Other error, with the message from twitch
+    Unknown(String),
+}
+
 #[doc(hidden)]
 pub mod status_code {
     use http::StatusCode;
@@ -97,7 +213,7 @@ pub mod scope {
         if let Some(scopes) = scopes {
             match scopes {
                 scopes if scopes.is_empty() || scopes.len() > 1 => Ok(Some(scopes)),
-                scopes if scopes.len() == 1 && scopes.get(0).unwrap().as_str() == "" => Ok(None),
+                scopes if scopes.len() == 1 && scopes.first().unwrap().as_str() == "" => Ok(None),
                 _ => Ok(Some(scopes)),
             }
         } else {
@@ -118,4 +234,175 @@ impl TwitchTokenResponse {
 
     /// Get the scopes from this response
     pub fn scopes(&self) -> Option<&[crate::Scope]> { self.scopes.as_deref() }
+
+    /// Decode the claims of the [ID token](TwitchTokenResponse::id_token) in this response, without verifying its signature.
+    ///
+    /// See [`IdTokenClaims::decode_unverified`]
+    pub fn id_token_claims(&self) -> Option<Result<IdTokenClaims, IdTokenError>> {
+        self.id_token
+            .as_deref()
+            .map(IdTokenClaims::decode_unverified)
+    }
+}
+
+/// Claims of an [OpenID Connect ID token](https://dev.twitch.tv/docs/authentication/getting-tokens-oidc/#requesting-claims)
+///
+/// Which of the optional claims are present depends on the `claims` requested with [`UserTokenBuilder::openid`](crate::tokens::UserTokenBuilder::openid).
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct IdTokenClaims {
+    /// Issuer of the token, `https://id.twitch.tv/oauth2`
+    pub iss: String,
+    /// User ID of the user
+    pub sub: twitch_types::UserId,
+    /// Client ID the token was issued to
+    pub aud: String,
+    /// Expiration time, as a unix timestamp
+    pub exp: u64,
+    /// Time the token was issued, as a unix timestamp
+    pub iat: u64,
+    /// The nonce sent when authorizing, see [`UserTokenBuilder::nonce`](crate::tokens::UserTokenBuilder::nonce)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Login of the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<twitch_types::UserName>,
+    /// Email of the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Whether twitch verified the email of the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    /// URL to the profile image of the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub picture: Option<String>,
+    /// When the profile of the user was last updated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+impl IdTokenClaims {
+    /// Decode the claims of an ID token, **without** verifying its signature.
+    ///
+    /// Only use the claims for display purposes, or after verifying the ID token by other means,
+    /// like receiving it directly from twitch over TLS in the authorization code flow.
+    pub fn decode_unverified(id_token: &str) -> Result<IdTokenClaims, IdTokenError> {
+        use base64::Engine;
+
+        let mut parts = id_token.split('.');
+        let payload = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(_), Some(payload), Some(_), None) => payload,
+            _ => return Err(IdTokenError::Malformed),
+        };
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_token_claims() {
+        use base64::Engine;
+
+        let encode = |json: &str| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json);
+        let id_token = format!(
+            "{}.{}.signature",
+            encode(r#"{"alg":"RS256","typ":"JWT","kid":"1"}"#),
+            encode(
+                r#"{"aud":"clientid","exp":1700000000,"iat":1699999100,"iss":"https://id.twitch.tv/oauth2","sub":"1337","nonce":"nonce","preferred_username":"justintv"}"#
+            )
+        );
+        let response: TwitchTokenResponse = serde_json::from_value(serde_json::json!({
+            "access_token": "token",
+            "expires_in": 3600,
+            "scope": ["openid"],
+            "token_type": "bearer",
+            "id_token": id_token,
+        }))
+        .unwrap();
+        assert_eq!(response.scopes(), Some(&[crate::Scope::OpenId][..]));
+        let claims = response.id_token_claims().unwrap().unwrap();
+        assert_eq!(claims.sub.as_str(), "1337");
+        assert_eq!(claims.aud, "clientid");
+        assert_eq!(claims.nonce.as_deref(), Some("nonce"));
+        assert_eq!(
+            claims.preferred_username.as_ref().map(|u| u.as_str()),
+            Some("justintv")
+        );
+        assert_eq!(claims.email, None);
+
+        assert!(matches!(
+            IdTokenClaims::decode_unverified("not.a.jwt.token"),
+            Err(IdTokenError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn token_type() {
+        let response = |token_type: &str| {
+            http::Response::builder()
+                .body(
+                    serde_json::json!({"access_token": "token", "token_type": token_type})
+                        .to_string(),
+                )
+                .unwrap()
+        };
+        assert!(TwitchTokenResponse::from_response(&response("bearer")).is_ok());
+        assert!(TwitchTokenResponse::from_response(&response("Bearer")).is_ok());
+        assert!(matches!(
+            TwitchTokenResponse::from_response(&response("mac")),
+            Err(RequestParseError::UnexpectedTokenType { found }) if found == "mac"
+        ));
+    }
+
+    #[test]
+    fn error_kind() {
+        let kind = |json: &str| {
+            serde_json::from_str::<TwitchTokenErrorResponse>(json)
+                .unwrap()
+                .kind()
+        };
+        assert_eq!(
+            kind(r#"{"status":403,"message":"invalid client secret"}"#),
+            TwitchErrorKind::InvalidClient
+        );
+        assert_eq!(
+            kind(r#"{"status":400,"message":"Invalid authorization code"}"#),
+            TwitchErrorKind::InvalidGrant
+        );
+        assert_eq!(
+            kind(
+                r#"{"status":400,"message":"Parameter redirect_uri does not match registered URI"}"#
+            ),
+            TwitchErrorKind::RedirectMismatch
+        );
+        assert_eq!(
+            kind(r#"{"status":400,"message":"invalid scope requested: 'chat:reed'"}"#),
+            TwitchErrorKind::InvalidScope
+        );
+        assert_eq!(
+            kind(r#"{"status":400,"message":"authorization_pending"}"#),
+            TwitchErrorKind::AuthorizationPending
+        );
+        assert_eq!(
+            kind(r#"{"status":400,"message":"slow_down"}"#),
+            TwitchErrorKind::SlowDown
+        );
+        assert_eq!(
+            kind(r#"{"status":400,"message":"access_denied"}"#),
+            TwitchErrorKind::AccessDenied
+        );
+        assert_eq!(
+            kind(r#"{"status":400,"message":"expired_token"}"#),
+            TwitchErrorKind::InvalidGrant
+        );
+        assert_eq!(
+            kind(r#"{"status":400,"message":"something else"}"#),
+            TwitchErrorKind::Unknown("something else".to_owned())
+        );
+    }
 }
@@ -29,6 +29,10 @@
 //!
 //! # About
 //!
+//! ## Prelude
+//!
+//! The most commonly used types and traits can be imported with `use twitch_oauth2::prelude::*;`, see [`prelude`].
+//!
 //! ## Scopes
 //!
 //! The library contains all known twitch oauth2 scopes in [`Scope`].
@@ -40,6 +44,10 @@
 //!
 //! Things like [`UserTokenBuilder`] can be used to create a token from scratch, via the [OAuth authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow)
 //!
+//! For devices without a browser or a way to receive a redirect, like CLI tools or TVs, use [`DeviceUserTokenBuilder`] for the [OAuth device code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#device-code-grant-flow)
+//!
+//! Local applications with a browser can enable the `local-server` feature and use `UserTokenBuilder::authenticate_local` to run the whole authorization code flow in one call.
+//!
 //! ## App access token
 //!
 //! Similar to [`UserToken`], a token with authorization as the twitch application can be created with
@@ -50,13 +58,38 @@
 //! To enable client features with a supported http library, enable the http library feature in `twitch_oauth2`, like `twitch_oauth2 = { features = ["reqwest"], version = "0.13.0" }`.
 //! If you're using [twitch_api](https://crates.io/crates/twitch_api), you can use its [`HelixClient`](https://docs.rs/twitch_api/latest/twitch_api/struct.HelixClient.html) instead of the underlying http client.
 //!
+//! No http library is enabled by default. The `reqwest`, `surf` and `isahc` features each enable only their own library,
+//! and the `client` feature enables the async functions without any library, for use with a custom `client::Client` implementation.
 //!
 //! This library can be used without any specific http client library (like if you don't want to use `await`),
 //! using methods like [`AppAccessToken::from_response`] and [`AppAccessToken::get_app_access_token_request`]
-//! or [`UserTokenBuilder::get_user_token_request`] and [`UserToken::from_response`]
+//! or [`UserTokenBuilder::get_user_token_request`] and [`UserToken::from_response`].
+//!
+//! To use the library without an async runtime, enable the `blocking` feature. The `blocking` module has blocking versions of the token functions,
+//! and `blocking::SyncClient` sends requests with any blocking http client, see the `blocking_app_access_token` example.
+//!
+//! ## Testing
+//!
+//! With the `mock_api` feature, the urls used for twitch endpoints can be overridden with environment variables,
+//! like `TWITCH_OAUTH2_URL` to set the root path or `TWITCH_OAUTH2_TOKEN_URL` for a specific endpoint, see [`AUTH_URL`], [`TOKEN_URL`], [`DEVICE_URL`], [`VALIDATE_URL`], [`REVOKE_URL`] and [`OIDC_DISCOVERY_URL`].
+//! This makes it possible to test against a mock server, like the [`twitch-cli` mock api](https://github.com/twitchdev/twitch-cli/blob/main/docs/mock-api.md).
+//! Without the feature, pass [`TwitchUrls`] to the `*_with_urls` functions and builder constructors, like [`UserTokenBuilder::new_with_urls`], to use other urls.
+//!
+//! To test without any server, enable the `mock_client` feature and use `client::MockClient` to return canned responses.
+//!
+//! ## `no_std` and WebAssembly
+//!
+//! This library requires `std`. The expiry of tokens is tracked with [`std::time::Instant`], or [`std::time::SystemTime`] for tokens using the system clock,
+//! neither of which are available on `wasm32-unknown-unknown` without a shim.
+//! The sans-io request and response functions, [`Scope`] and the error types do not depend on the clock.
+#[cfg(feature = "blocking")]
+pub mod blocking;
 #[cfg(feature = "client")]
 pub mod client;
 pub mod id;
+#[cfg(feature = "oidc")]
+pub mod oidc;
+pub mod prelude;
 pub mod scopes;
 pub mod tokens;
 pub mod types;
@@ -67,19 +100,26 @@ use id::TwitchTokenErrorResponse;
 use tokens::errors::{RefreshTokenError, RevokeTokenError, ValidationError};
 
 #[doc(inline)]
-pub use scopes::{Scope, Validator};
+pub use scopes::{Scope, ScopeSet, Validator};
 #[doc(inline)]
 pub use tokens::{
-    AppAccessToken, ImplicitUserTokenBuilder, TwitchToken, UserToken, UserTokenBuilder,
-    ValidatedToken,
+    AppAccessToken, DeviceUserTokenBuilder, ImplicitUserTokenBuilder, TwitchToken, UserToken,
+    UserTokenBuilder, ValidatedToken,
 };
 
+pub use twitch_types;
 pub use url;
 
-pub use types::{AccessToken, ClientId, ClientSecret, CsrfToken, RefreshToken};
+pub use types::{
+    AccessToken, AuthorizationCode, ClientCredentials, ClientId, ClientSecret, CsrfToken,
+    PkceCodeVerifier, RefreshToken,
+};
 
 #[doc(hidden)]
-pub use types::{AccessTokenRef, ClientIdRef, ClientSecretRef, CsrfTokenRef, RefreshTokenRef};
+pub use types::{
+    AccessTokenRef, AuthorizationCodeRef, ClientIdRef, ClientSecretRef, CsrfTokenRef,
+    PkceCodeVerifierRef, RefreshTokenRef,
+};
 
 #[cfg(feature = "client")]
 use self::client::Client;
@@ -125,6 +165,17 @@ pub static AUTH_URL: once_cell::sync::Lazy<url::Url> = mock_env_url!("TWITCH_OAU
 pub static TOKEN_URL: once_cell::sync::Lazy<url::Url> = mock_env_url!("TWITCH_OAUTH2_TOKEN_URL", {
     TWITCH_OAUTH2_URL.to_string() + "token"
 },);
+/// Device URL (`https://id.twitch.tv/oauth2/device`) for `id.twitch.tv`
+///
+/// Can be overridden when feature `mock_api` is enabled with environment variable `TWITCH_OAUTH2_URL` to set the root path, or with `TWITCH_OAUTH2_DEVICE_URL` to override the base (`https://id.twitch.tv/oauth2/`) url.
+///
+/// # Examples
+///
+/// Set the environment variable `TWITCH_OAUTH2_URL` to `http://localhost:8080/auth/` to use [`twitch-cli` mock](https://github.com/twitchdev/twitch-cli/blob/main/docs/mock-api.md) endpoints.
+pub static DEVICE_URL: once_cell::sync::Lazy<url::Url> =
+    mock_env_url!("TWITCH_OAUTH2_DEVICE_URL", {
+        TWITCH_OAUTH2_URL.to_string() + "device"
+    },);
 /// Validation URL (`https://id.twitch.tv/oauth2/validate`) for `id.twitch.tv`
 ///
 /// Can be overridden when feature `mock_api` is enabled with environment variable `TWITCH_OAUTH2_URL` to set the root path, or with `TWITCH_OAUTH2_VALIDATE_URL` to override the base (`https://id.twitch.tv/oauth2/`) url.
@@ -136,6 +187,13 @@ pub static VALIDATE_URL: once_cell::sync::Lazy<url::Url> =
     mock_env_url!("TWITCH_OAUTH2_VALIDATE_URL", {
         TWITCH_OAUTH2_URL.to_string() + "validate"
     },);
+/// OpenID Connect discovery URL (`https://id.twitch.tv/oauth2/.well-known/openid-configuration`) for `id.twitch.tv`
+///
+/// Can be overridden when feature `mock_api` is enabled with environment variable `TWITCH_OAUTH2_URL` to set the root path, or with `TWITCH_OAUTH2_OIDC_DISCOVERY_URL` to override the base (`https://id.twitch.tv/oauth2/`) url.
+pub static OIDC_DISCOVERY_URL: once_cell::sync::Lazy<url::Url> =
+    mock_env_url!("TWITCH_OAUTH2_OIDC_DISCOVERY_URL", {
+        TWITCH_OAUTH2_URL.to_string() + ".well-known/openid-configuration"
+    },);
 /// Revokation URL (`https://id.twitch.tv/oauth2/revoke`) for `id.twitch.tv`
 ///
 /// Can be overridden when feature `mock_api` is enabled with environment variable `TWITCH_OAUTH2_URL` to set the root path, or with `TWITCH_OAUTH2_REVOKE_URL` to override the base (`https://id.twitch.tv/oauth2/`) url.
@@ -148,11 +206,75 @@ pub static REVOKE_URL: once_cell::sync::Lazy<url::Url> =
         TWITCH_OAUTH2_URL.to_string() + "revoke"
     },);
 
+/// The twitch endpoints used by the builders and token functions
+///
+/// Defaults to [`AUTH_URL`], [`TOKEN_URL`], [`VALIDATE_URL`], [`REVOKE_URL`] and [`DEVICE_URL`].
+/// Pass it to functions like [`UserTokenBuilder::new_with_urls`] or [`AccessTokenRef::validate_token_with_urls`] to talk to another server, like a fake twitch in tests.
+///
+/// # Examples
+///
+/// ```rust
+/// use twitch_oauth2::{tokens::UserTokenBuilder, TwitchUrls};
+///
+/// let urls = TwitchUrls::from_root(&url::Url::parse("http://localhost:8080/auth/")?)?;
+/// assert_eq!(urls.token_url.as_str(), "http://localhost:8080/auth/token");
+///
+/// let redirect_url = url::Url::parse("http://localhost/twitch/register")?;
+/// let builder =
+///     UserTokenBuilder::new_with_urls("myclientid", "myclientsecret", redirect_url, urls);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TwitchUrls {
+    /// Authorization url, defaults to [`AUTH_URL`]
+    pub auth_url: url::Url,
+    /// Token url, defaults to [`TOKEN_URL`]
+    pub token_url: url::Url,
+    /// Validation url, defaults to [`VALIDATE_URL`]
+    pub validate_url: url::Url,
+    /// Revokation url, defaults to [`REVOKE_URL`]
+    pub revoke_url: url::Url,
+    /// Device url, defaults to [`DEVICE_URL`]
+    pub device_url: url::Url,
+}
+
+impl TwitchUrls {
+    /// Create the urls from a root path, like `https://id.twitch.tv/oauth2/`.
+    ///
+    /// The root should end with a `/`, otherwise the last segment of its path is replaced.
+    pub fn from_root(root: &url::Url) -> Result<TwitchUrls, url::ParseError> {
+        Ok(TwitchUrls {
+            auth_url: root.join("authorize")?,
+            token_url: root.join("token")?,
+            validate_url: root.join("validate")?,
+            revoke_url: root.join("revoke")?,
+            device_url: root.join("device")?,
+        })
+    }
+}
+
+impl Default for TwitchUrls {
+    fn default() -> Self {
+        TwitchUrls {
+            auth_url: AUTH_URL.clone(),
+            token_url: TOKEN_URL.clone(),
+            validate_url: VALIDATE_URL.clone(),
+            revoke_url: REVOKE_URL.clone(),
+            device_url: DEVICE_URL.clone(),
+        }
+    }
+}
+
 impl AccessTokenRef {
     /// Get the request needed to validate this token.
     ///
     /// Parse the response from this endpoint with [ValidatedToken::from_response](crate::ValidatedToken::from_response)
     pub fn validate_token_request(&self) -> http::Request<Vec<u8>> {
+        self.validate_token_request_with_urls(&TwitchUrls::default())
+    }
+
+    /// Get the request needed to validate this token, sent to [`TwitchUrls::validate_url`].
+    pub fn validate_token_request_with_urls(&self, urls: &TwitchUrls) -> http::Request<Vec<u8>> {
         use http::{header::AUTHORIZATION, HeaderMap, Method};
 
         let auth_header = format!("OAuth {}", self.secret());
@@ -165,7 +287,7 @@ impl AccessTokenRef {
         );
 
         crate::construct_request::<&[(String, String)], _, _>(
-            &crate::VALIDATE_URL,
+            &urls.validate_url,
             &[],
             headers,
             Method::GET,
@@ -176,25 +298,76 @@ impl AccessTokenRef {
     /// Validate this token.
     ///
     /// Should be checked on regularly, according to <https://dev.twitch.tv/docs/authentication/validate-tokens/>
+    ///
+    /// This can also be used to check who a token belongs to, without creating a [`UserToken`].
+    ///
+    /// The client is borrowed, so create it once and reuse it for every validation to keep its connection pool.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use twitch_oauth2::AccessToken;
+    /// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .build()?;
+    /// # );
+    /// let token = AccessToken::from("sometokenherewhichisvalidornot");
+    /// let validated = token.validate_token(&client).await?;
+    /// println!(
+    ///     "token belongs to {:?} ({:?}) with scopes {:?}",
+    ///     validated.login, validated.user_id, validated.scopes
+    /// );
+    /// # Ok::<(), Box<dyn std::error::Error>>(())};
+    /// ```
     #[cfg(feature = "client")]
-    pub async fn validate_token<'a, C>(
+    pub async fn validate_token<C>(
         &self,
-        client: &'a C,
+        client: &C,
     ) -> Result<ValidatedToken, ValidationError<<C as Client>::Error>>
     where
         C: Client,
     {
-        let req = self.validate_token_request();
+        self.validate_token_with_urls(client, &TwitchUrls::default())
+            .await
+    }
 
+    /// Validate this token with [`TwitchUrls::validate_url`], see [`validate_token`](AccessTokenRef::validate_token).
+    #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(status), err(level = "warn"))
+    )]
+    pub async fn validate_token_with_urls<C>(
+        &self,
+        client: &C,
+        urls: &TwitchUrls,
+    ) -> Result<ValidatedToken, ValidationError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let req = self.validate_token_request_with_urls(urls);
+
+        trace_request(&req);
         let resp = client.req(req).await.map_err(ValidationError::Request)?;
+        trace_response(&resp);
         if resp.status() == StatusCode::UNAUTHORIZED {
-            return Err(ValidationError::NotAuthorized);
+            return Err(ValidationError::unauthorized(&resp));
         }
         ValidatedToken::from_response(&resp).map_err(|v| v.into_other())
     }
 
     /// Get the request needed to revoke this token.
     pub fn revoke_token_request(&self, client_id: &ClientId) -> http::Request<Vec<u8>> {
+        self.revoke_token_request_with_urls(client_id, &TwitchUrls::default())
+    }
+
+    /// Get the request needed to revoke this token, sent to [`TwitchUrls::revoke_url`].
+    pub fn revoke_token_request_with_urls(
+        &self,
+        client_id: &ClientId,
+        urls: &TwitchUrls,
+    ) -> http::Request<Vec<u8>> {
         use http::{HeaderMap, Method};
         use std::collections::HashMap;
         let mut params = HashMap::new();
@@ -202,7 +375,7 @@ impl AccessTokenRef {
         params.insert("token", self.secret());
 
         construct_request(
-            &crate::REVOKE_URL,
+            &urls.revoke_url,
             &params,
             HeaderMap::new(),
             Method::POST,
@@ -212,17 +385,35 @@ impl AccessTokenRef {
 
     /// Revoke the token.
     ///
+    /// If twitch rejects the request, for example with `400 Bad Request` on an invalid token, the error response is returned in
+    /// [`RevokeTokenError::RequestParseError`] as [`RequestParseError::TwitchError`].
+    ///
     /// See <https://dev.twitch.tv/docs/authentication/revoke-tokens/>
     #[cfg(feature = "client")]
-    pub async fn revoke_token<'a, C>(
+    pub async fn revoke_token<C>(
+        &self,
+        http_client: &C,
+        client_id: &ClientId,
+    ) -> Result<(), RevokeTokenError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        self.revoke_token_with_urls(http_client, client_id, &TwitchUrls::default())
+            .await
+    }
+
+    /// Revoke the token with [`TwitchUrls::revoke_url`], see [`revoke_token`](AccessTokenRef::revoke_token).
+    #[cfg(feature = "client")]
+    pub async fn revoke_token_with_urls<C>(
         &self,
-        http_client: &'a C,
+        http_client: &C,
         client_id: &ClientId,
+        urls: &TwitchUrls,
     ) -> Result<(), RevokeTokenError<<C as Client>::Error>>
     where
         C: Client,
     {
-        let req = self.revoke_token_request(client_id);
+        let req = self.revoke_token_request_with_urls(client_id, urls);
 
         let resp = http_client
             .req(req)
@@ -242,6 +433,16 @@ impl RefreshTokenRef {
         &self,
         client_id: &ClientId,
         client_secret: &ClientSecret,
+    ) -> http::Request<Vec<u8>> {
+        self.refresh_token_request_with_urls(client_id, client_secret, &TwitchUrls::default())
+    }
+
+    /// Get the request needed to refresh this token, sent to [`TwitchUrls::token_url`].
+    pub fn refresh_token_request_with_urls(
+        &self,
+        client_id: &ClientId,
+        client_secret: &ClientSecret,
+        urls: &TwitchUrls,
     ) -> http::Request<Vec<u8>> {
         use http::{HeaderMap, Method};
         use std::collections::HashMap;
@@ -253,7 +454,7 @@ impl RefreshTokenRef {
         params.insert("refresh_token", self.secret());
 
         construct_request(
-            &crate::TOKEN_URL,
+            &urls.token_url,
             &params,
             HeaderMap::new(),
             Method::POST,
@@ -265,9 +466,9 @@ impl RefreshTokenRef {
     ///
     /// See <https://dev.twitch.tv/docs/authentication/refresh-tokens>
     #[cfg(feature = "client")]
-    pub async fn refresh_token<'a, C>(
+    pub async fn refresh_token<C>(
         &self,
-        http_client: &'a C,
+        http_client: &C,
         client_id: &ClientId,
         client_secret: &ClientSecret,
     ) -> Result<
@@ -277,12 +478,42 @@ impl RefreshTokenRef {
     where
         C: Client,
     {
-        let req = self.refresh_token_request(client_id, client_secret);
+        self.refresh_token_with_urls(
+            http_client,
+            client_id,
+            client_secret,
+            &TwitchUrls::default(),
+        )
+        .await
+    }
 
+    /// Refresh the token with [`TwitchUrls::token_url`], see [`refresh_token`](RefreshTokenRef::refresh_token).
+    #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(client_id = %client_id, status), err(level = "warn"))
+    )]
+    pub async fn refresh_token_with_urls<C>(
+        &self,
+        http_client: &C,
+        client_id: &ClientId,
+        client_secret: &ClientSecret,
+        urls: &TwitchUrls,
+    ) -> Result<
+        (AccessToken, std::time::Duration, Option<RefreshToken>),
+        RefreshTokenError<<C as Client>::Error>,
+    >
+    where
+        C: Client,
+    {
+        let req = self.refresh_token_request_with_urls(client_id, client_secret, urls);
+
+        trace_request(&req);
         let resp = http_client
             .req(req)
             .await
             .map_err(RefreshTokenError::RequestError)?;
+        trace_response(&resp);
         let res = id::TwitchTokenResponse::from_response(&resp)?;
 
         let expires_in = res.expires_in().ok_or(RefreshTokenError::NoExpiration)?;
@@ -292,6 +523,115 @@ impl RefreshTokenRef {
     }
 }
 
+/// Validate multiple tokens concurrently, with at most `concurrency` requests in flight at once.
+///
+/// Results are returned in the same order as `tokens`. A failed validation does not stop the other tokens from being validated.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use twitch_oauth2::AccessToken;
+/// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::builder()
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()?;
+/// # );
+/// let tokens = vec![AccessToken::from("token1"), AccessToken::from("token2")];
+/// for (token, result) in tokens
+///     .iter()
+///     .zip(twitch_oauth2::validate_tokens(&client, &tokens, 10).await)
+/// {
+///     if result.is_err() {
+///         println!("{token:?} is not valid");
+///     }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())};
+/// ```
+#[cfg(feature = "client")]
+pub async fn validate_tokens<C>(
+    client: &C,
+    tokens: &[AccessToken],
+    concurrency: usize,
+) -> Vec<Result<ValidatedToken, ValidationError<<C as Client>::Error>>>
+where
+    C: Client,
+{
+    validate_tokens_with_urls(client, tokens, concurrency, &TwitchUrls::default()).await
+}
+
+/// Validate multiple tokens concurrently with [`TwitchUrls::validate_url`], see [`validate_tokens`].
+#[cfg(feature = "client")]
+pub async fn validate_tokens_with_urls<C>(
+    client: &C,
+    tokens: &[AccessToken],
+    concurrency: usize,
+    urls: &TwitchUrls,
+) -> Vec<Result<ValidatedToken, ValidationError<<C as Client>::Error>>>
+where
+    C: Client,
+{
+    use std::{future::Future, pin::Pin, task::Poll};
+
+    type Validation<'a, E> =
+        Pin<Box<dyn Future<Output = Result<ValidatedToken, ValidationError<E>>> + Send + 'a>>;
+
+    let concurrency = concurrency.max(1);
+    let mut results: Vec<Option<_>> = tokens.iter().map(|_| None).collect();
+    let mut pending = tokens.iter().enumerate();
+    let mut in_flight: Vec<(usize, Validation<'_, C::Error>)> = Vec::with_capacity(concurrency);
+    std::future::poll_fn(|cx| loop {
+        while in_flight.len() < concurrency {
+            let Some((i, token)) = pending.next() else {
+                break;
+            };
+            in_flight.push((i, Box::pin(token.validate_token_with_urls(client, urls))));
+        }
+        let before = in_flight.len();
+        in_flight.retain_mut(|(i, validation)| match validation.as_mut().poll(cx) {
+            Poll::Ready(result) => {
+                results[*i] = Some(result);
+                false
+            }
+            Poll::Pending => true,
+        });
+        if in_flight.is_empty() && pending.len() == 0 {
+            return Poll::Ready(());
+        }
+        if in_flight.len() == before {
+            return Poll::Pending;
+        }
+    })
+    .await;
+    results
+        .into_iter()
+        .map(|result| result.expect("all tokens should have been validated"))
+        .collect()
+}
+
+/// Log a request that is about to be sent.
+///
+/// Only the method and path are logged, as the query can contain secrets.
+#[cfg(feature = "client")]
+pub(crate) fn trace_request<B>(_req: &http::Request<B>) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(method = %_req.method(), path = _req.uri().path(), "sending request");
+}
+
+/// Record the status of a response in the current span and log it.
+#[cfg(feature = "client")]
+pub(crate) fn trace_response<B>(_resp: &http::Response<B>) {
+    #[cfg(feature = "tracing")]
+    {
+        let status = _resp.status();
+        tracing::Span::current().record("status", status.as_u16());
+        if status.is_success() {
+            tracing::debug!(status = status.as_u16(), "received response");
+        } else {
+            tracing::warn!(status = status.as_u16(), "twitch returned an error");
+        }
+    }
+}
+
 /// Construct a request that accepts `application/json` on default
 fn construct_request<I, K, V>(
     url: &url::Url,
@@ -310,9 +650,7 @@ where
     url.query_pairs_mut().extend_pairs(params);
     let url: String = url.into();
     let mut req = http::Request::builder().method(method).uri(url);
-    req.headers_mut()
-        .map(|h| h.extend(headers.into_iter()))
-        .unwrap();
+    req.headers_mut().map(|h| h.extend(headers)).unwrap();
     req.headers_mut()
         .map(|h| {
             if !h.contains_key(http::header::ACCEPT) {
@@ -323,19 +661,89 @@ where
     req.body(body).unwrap()
 }
 
+/// Construct a request that sends `params` as an `application/x-www-form-urlencoded` body, and accepts `application/json` on default
+fn construct_form_request<I, K, V>(
+    url: &url::Url,
+    params: I,
+    mut headers: http::HeaderMap,
+    method: http::Method,
+) -> http::Request<Vec<u8>>
+where
+    I: std::iter::IntoIterator,
+    I::Item: std::borrow::Borrow<(K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+    headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/x-www-form-urlencoded"),
+    );
+    if !headers.contains_key(http::header::ACCEPT) {
+        headers.insert(
+            http::header::ACCEPT,
+            http::HeaderValue::from_static("application/json"),
+        );
+    }
+    let mut req = http::Request::builder().method(method).uri(url.as_str());
+    req.headers_mut().map(|h| h.extend(headers)).unwrap();
+    req.body(body.into_bytes()).unwrap()
+}
+
 /// Parses a response, validating it and returning the response if all ok.
 pub(crate) fn parse_token_response_raw<B: AsRef<[u8]>>(
     resp: &http::Response<B>,
 ) -> Result<&http::Response<B>, RequestParseError> {
+    if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(RequestParseError::Ratelimited {
+            retry_after: retry_after(resp.headers()),
+        });
+    }
     match serde_json::from_slice::<TwitchTokenErrorResponse>(resp.body().as_ref()) {
         Err(_) => match resp.status() {
             StatusCode::OK => Ok(resp),
-            _ => Err(RequestParseError::Other(resp.status())),
+            status if resp.body().as_ref().is_empty() => Err(RequestParseError::Other(status)),
+            status => Err(RequestParseError::UnexpectedResponse {
+                status,
+                body: truncate_body(resp.body().as_ref()),
+            }),
         },
         Ok(twitch_err) => Err(RequestParseError::TwitchError(twitch_err)),
     }
 }
 
+/// Get how long to wait before retrying from the `Retry-After` or `Ratelimit-Reset` headers
+fn retry_after(headers: &http::HeaderMap) -> Option<std::time::Duration> {
+    let header =
+        |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.trim().parse().ok() };
+    if let Some(seconds) = header("retry-after") {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+    // Ratelimit-Reset is a unix timestamp
+    let reset = std::time::UNIX_EPOCH + std::time::Duration::from_secs(header("ratelimit-reset")?);
+    Some(
+        reset
+            .duration_since(std::time::SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// Get the body as text, truncated to at most 256 bytes
+fn truncate_body(body: &[u8]) -> String {
+    const MAX_LEN: usize = 256;
+    let body = String::from_utf8_lossy(body);
+    if body.len() <= MAX_LEN {
+        return body.into_owned();
+    }
+    let mut end = MAX_LEN;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &body[..end])
+}
+
 /// Parses a response, validating it and returning json deserialized response
 pub(crate) fn parse_response<T: serde::de::DeserializeOwned, B: AsRef<[u8]>>(
     resp: &http::Response<B>,
@@ -350,7 +758,11 @@ pub(crate) fn parse_response<T: serde::de::DeserializeOwned, B: AsRef<[u8]>>(
             });
         }
     }
-    serde_json::from_slice(body).map_err(Into::into)
+    serde_json::from_slice(body).map_err(|error| RequestParseError::InvalidBody {
+        status: resp.status(),
+        body: truncate_body(body),
+        error,
+    })
 }
 
 /// Errors from parsing responses
@@ -368,4 +780,31 @@ pub enum RequestParseError {
     },
     /// twitch returned an unexpected status code: {0}
     Other(StatusCode),
+    /// twitch ratelimited the request, retry after {retry_after:?}
+    Ratelimited {
+        /// How long to wait before retrying, if twitch specified it
+        retry_after: Option<std::time::Duration>,
+    },
+    /// twitch returned an unexpected status code: {status} - {body}
+    UnexpectedResponse {
+        /// Status code of the response
+        status: StatusCode,
+        /// Body of the response, truncated
+        body: String,
+    },
+    /// twitch returned a token of type `{found}`, expected `bearer`
+    UnexpectedTokenType {
+        /// Found `token_type`
+        found: String,
+    },
+    /// could not deserialize response with status code {status}: {body:?}
+    InvalidBody {
+        /// Status code of the response
+        status: StatusCode,
+        /// Body of the response, truncated
+        body: String,
+        /// Error from deserializing the body
+        #[source]
+        error: serde_json::Error,
+    },
 }
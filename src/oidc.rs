@@ -0,0 +1,238 @@
+//! Verification of [OpenID Connect](https://dev.twitch.tv/docs/authentication/getting-tokens-oidc/) ID tokens
+//!
+//! Request an ID token with [`UserTokenBuilder::openid`](crate::tokens::UserTokenBuilder::openid), and verify it with [`verify_id_token`].
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    client::Client, id::IdTokenClaims, tokens::errors::IdTokenVerificationError, ClientIdRef,
+};
+
+/// The keys used by twitch to sign ID tokens, cached until they expire according to the `Cache-Control` of the response.
+static JWKS: once_cell::sync::Lazy<std::sync::Mutex<Option<CachedJwks>>> =
+    once_cell::sync::Lazy::new(Default::default);
+
+#[derive(Clone)]
+struct CachedJwks {
+    issuer: String,
+    keys: jsonwebtoken::jwk::JwkSet,
+    expires: Instant,
+    fetched: Instant,
+}
+
+/// Minimum time between fetching the keys again because of an unknown key id.
+const UNKNOWN_KEY_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Relevant parts of the [discovery document](https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata)
+#[derive(serde_derive::Deserialize)]
+struct Discovery {
+    issuer: String,
+    jwks_uri: String,
+}
+
+/// Verify an ID token from twitch, returning its claims.
+///
+/// The signing keys are fetched with the [discovery document](crate::OIDC_DISCOVERY_URL) and cached according to the `Cache-Control` header of the response.
+/// An unknown key id fetches the keys again, at most once a minute.
+/// The signature must be `RS256` and the `aud`, `iss` and `exp` claims are validated.
+/// If `nonce` is set, it must match the nonce in the ID token, see [`UserTokenBuilder::nonce`](crate::tokens::UserTokenBuilder::nonce).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::builder()
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()?;
+/// # );
+/// # let (id_token, nonce) = ("", "");
+/// let client_id = twitch_oauth2::ClientId::from("myclientid");
+/// let claims =
+///     twitch_oauth2::oidc::verify_id_token(&client, id_token, &client_id, Some(nonce)).await?;
+/// println!("logged in as {}", claims.sub);
+/// # Ok::<(), Box<dyn std::error::Error>>(())};
+/// ```
+pub async fn verify_id_token<C>(
+    http_client: &C,
+    id_token: &str,
+    client_id: &ClientIdRef,
+    nonce: Option<&str>,
+) -> Result<IdTokenClaims, IdTokenVerificationError<<C as Client>::Error>>
+where
+    C: Client,
+{
+    let header = jsonwebtoken::decode_header(id_token)?;
+    let kid = header.kid.ok_or(IdTokenVerificationError::UnknownKey)?;
+
+    let cached = JWKS
+        .lock()
+        .expect("jwks cache should not be poisoned")
+        .clone();
+    let jwks = match cached {
+        Some(jwks) if jwks.expires > Instant::now() && jwks.keys.find(&kid).is_some() => jwks,
+        // Fetch the keys again if the key is missing, twitch could have rotated them.
+        // Limit how often this happens, so tokens with made up key ids can't make us send requests to twitch every time.
+        Some(jwks)
+            if jwks.keys.find(&kid).is_none()
+                && jwks.fetched.elapsed() < UNKNOWN_KEY_REFETCH_INTERVAL =>
+        {
+            return Err(IdTokenVerificationError::UnknownKey)
+        }
+        _ => fetch_jwks(http_client).await?,
+    };
+    let key = jwks
+        .keys
+        .find(&kid)
+        .ok_or(IdTokenVerificationError::UnknownKey)?;
+
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256);
+    validation.set_audience(&[client_id.as_str()]);
+    validation.set_issuer(&[&jwks.issuer]);
+    let claims = jsonwebtoken::decode::<IdTokenClaims>(
+        id_token,
+        &jsonwebtoken::DecodingKey::from_jwk(key)?,
+        &validation,
+    )?
+    .claims;
+
+    if nonce.is_some() && claims.nonce.as_deref() != nonce {
+        return Err(IdTokenVerificationError::NonceMismatch);
+    }
+    Ok(claims)
+}
+
+/// Fetch the signing keys and store them in the cache
+async fn fetch_jwks<C: Client>(
+    http_client: &C,
+) -> Result<CachedJwks, IdTokenVerificationError<<C as Client>::Error>> {
+    let discovery: Discovery = serde_json::from_slice(
+        crate::parse_token_response_raw(&get(http_client, &crate::OIDC_DISCOVERY_URL).await?)?
+            .body(),
+    )
+    .map_err(crate::RequestParseError::from)?;
+
+    let jwks_uri = url::Url::parse(&discovery.jwks_uri)?;
+    let resp = get(http_client, &jwks_uri).await?;
+    let keys = serde_json::from_slice(crate::parse_token_response_raw(&resp)?.body())
+        .map_err(crate::RequestParseError::from)?;
+    let jwks = CachedJwks {
+        issuer: discovery.issuer,
+        keys,
+        expires: Instant::now() + max_age(&resp).unwrap_or_default(),
+        fetched: Instant::now(),
+    };
+    *JWKS.lock().expect("jwks cache should not be poisoned") = Some(jwks.clone());
+    Ok(jwks)
+}
+
+async fn get<C: Client>(
+    http_client: &C,
+    url: &url::Url,
+) -> Result<http::Response<Vec<u8>>, IdTokenVerificationError<<C as Client>::Error>> {
+    let req = crate::construct_request::<&[(String, String)], _, _>(
+        url,
+        &[],
+        http::HeaderMap::new(),
+        http::Method::GET,
+        vec![],
+    );
+    crate::trace_request(&req);
+    let resp = http_client
+        .req(req)
+        .await
+        .map_err(IdTokenVerificationError::Request)?;
+    crate::trace_response(&resp);
+    Ok(resp)
+}
+
+/// Get the `max-age` of the `Cache-Control` header, `None` if the response should not be cached.
+fn max_age<B>(resp: &http::Response<B>) -> Option<Duration> {
+    let cache_control = resp
+        .headers()
+        .get(http::header::CACHE_CONTROL)?
+        .to_str()
+        .ok()?;
+    let directives = cache_control.split(',').map(str::trim);
+    if directives
+        .clone()
+        .any(|d| d.eq_ignore_ascii_case("no-store") || d.eq_ignore_ascii_case("no-cache"))
+    {
+        return None;
+    }
+    directives
+        .filter_map(|d| d.strip_prefix("max-age="))
+        .find_map(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+
+    fn response(cache_control: &str) -> http::Response<Vec<u8>> {
+        http::Response::builder()
+            .header(http::header::CACHE_CONTROL, cache_control)
+            .body(vec![])
+            .unwrap()
+    }
+
+    #[test]
+    fn cache_control() {
+        assert_eq!(
+            max_age(&response("public, max-age=3600")),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(max_age(&response("no-store, max-age=3600")), None);
+        assert_eq!(max_age(&response("public")), None);
+    }
+
+    #[tokio::test]
+    async fn unknown_key() {
+        use base64::Engine;
+
+        let encode = |json: &str| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json);
+        let id_token = format!(
+            "{}.{}.signature",
+            encode(r#"{"alg":"RS256","typ":"JWT","kid":"1"}"#),
+            encode(
+                r#"{"aud":"clientid","exp":1700000000,"iat":1699999100,"iss":"https://id.twitch.tv/oauth2","sub":"1337"}"#
+            )
+        );
+        let jwks_uri = url::Url::parse("https://id.twitch.tv/oauth2/keys").unwrap();
+        let client = MockClient::new()
+            .with_response(
+                http::Method::GET,
+                &crate::OIDC_DISCOVERY_URL,
+                http::Response::builder()
+                    .body(
+                        serde_json::json!({"issuer": "https://id.twitch.tv/oauth2", "jwks_uri": jwks_uri.as_str()})
+                            .to_string()
+                            .into_bytes(),
+                    )
+                    .unwrap(),
+            )
+            .with_response(
+                http::Method::GET,
+                &jwks_uri,
+                http::Response::builder()
+                    .body(br#"{"keys":[]}"#.to_vec())
+                    .unwrap(),
+            );
+        let err = verify_id_token(&client, &id_token, &crate::ClientId::from("clientid"), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IdTokenVerificationError::UnknownKey));
+
+        // The keys were just fetched, so they are not fetched again for the unknown key.
+        let err = verify_id_token(
+            &MockClient::new(),
+            &id_token,
+            &crate::ClientId::from("clientid"),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, IdTokenVerificationError::UnknownKey));
+    }
+}
@@ -0,0 +1,29 @@
+//! Commonly used types and traits, glob import with `use twitch_oauth2::prelude::*;`
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use twitch_oauth2::prelude::*;
+//!
+//! # fn t() -> UserToken {todo!()}
+//! let token: UserToken = t();
+//! println!("scopes: {:?}", token.scopes());
+//! ```
+
+#[cfg(feature = "client")]
+#[doc(no_inline)]
+pub use crate::client::Client;
+#[doc(no_inline)]
+pub use crate::tokens::errors::ValidationError;
+#[cfg(feature = "client")]
+#[doc(no_inline)]
+pub use crate::tokens::errors::{
+    AppAccessTokenError, DeviceUserTokenExchangeError, ImplicitUserTokenExchangeError,
+    RefreshTokenError, RevokeTokenError, UserTokenExchangeError,
+};
+#[doc(no_inline)]
+pub use crate::{
+    AccessToken, AppAccessToken, ClientId, ClientSecret, DeviceUserTokenBuilder,
+    ImplicitUserTokenBuilder, RefreshToken, Scope, TwitchToken, UserToken, UserTokenBuilder,
+    ValidatedToken,
+};
@@ -1,5 +1,7 @@
 //! Module for all possible scopes in twitch.
+pub mod set;
 pub mod validator;
+pub use set::ScopeSet;
 pub use validator::Validator;
 
 use serde_derive::{Deserialize, Serialize};
@@ -13,6 +15,12 @@ macro_rules! scope_impls {
     (@omit $i:ident) => {
         Self::$i
     };
+    (@listed #[deprecated($depr:tt)] $i:ident) => {
+        assert!(!Scope::all_slice().contains(&Scope::$i), "deprecated `Scope::{}` is listed", stringify!($i));
+    };
+    (@listed $i:ident) => {
+        assert!(Scope::all_slice().contains(&Scope::$i), "`Scope::{}` is not listed", stringify!($i));
+    };
 
     ($($(#[cfg(($cfg:meta))])* $(#[deprecated($depr:meta)])? $i:ident,scope: $rename:literal, doc: $doc:literal);* $(;)? ) => {
         #[doc = "Scopes for twitch."]
@@ -55,6 +63,8 @@ macro_rules! scope_impls {
         impl Scope {
             #[doc = "Get a vec of all defined twitch [Scopes][Scope]."]
             #[doc = "\n\n"]
+            #[doc = "Deprecated scopes and [`Scope::Other`] are not included, see [`Scope::all_slice`] for a `&'static` slice."]
+            #[doc = "\n\n"]
             #[doc = "Please note that this may not work for you, as some auth flows and \"apis\" don't accept all scopes"]
             pub fn all() -> Vec<Scope> {
                 vec![
@@ -66,6 +76,8 @@ macro_rules! scope_impls {
 
             #[doc = "Get a slice of all defined twitch [Scopes][Scope]."]
             #[doc = "\n\n"]
+            #[doc = "Deprecated scopes and [`Scope::Other`] are not included."]
+            #[doc = "\n\n"]
             #[doc = "Please note that this may not work for you, as some auth flows and \"apis\" don't accept all scopes"]
             pub const fn all_slice() -> &'static [Scope] {
                 &[
@@ -75,7 +87,9 @@ macro_rules! scope_impls {
                 ]
             }
 
-            #[doc = "Get a description for the token"]
+            #[doc = "Get a description of what the scope allows, as described in the [twitch documentation](https://dev.twitch.tv/docs/authentication/scopes/)."]
+            #[doc = "\n\n"]
+            #[doc = "Useful for showing the user what they are granting. Returns `\"unknown scope\"` for [`Scope::Other`]."]
             pub const fn description(&self) -> &'static str {
                 #![allow(deprecated)]
 
@@ -133,6 +147,18 @@ macro_rules! scope_impls {
                 }
             }
         }
+        #[test]
+        #[cfg(test)]
+        fn all_listed() {
+            #![allow(deprecated)]
+            $(
+                $(#[cfg($cfg)])*
+                scope_impls!(@listed $(#[deprecated($depr)])* $i);
+            )*
+            assert!(!Scope::all_slice().iter().any(|s| matches!(s, Scope::Other(_))));
+            assert_eq!(Scope::all(), Scope::all_slice());
+        }
+
         #[test]
         #[cfg(test)]
         fn sorted() {
@@ -204,6 +230,7 @@ scope_impls!(
     ModeratorReadFollowers,         scope: "moderator:read:followers",          doc: "Read the followers of a broadcaster.";
     ModeratorReadShieldMode,        scope: "moderator:read:shield_mode",        doc: "View a broadcaster’s Shield Mode status.";
     ModeratorReadShoutouts,         scope: "moderator:read:shoutouts",          doc: "View a broadcaster’s shoutouts.";
+    OpenId,                         scope: "openid",                            doc: "Request an ID token and user info with [OpenID Connect](https://dev.twitch.tv/docs/authentication/getting-tokens-oidc/).";
     UserBot,                        scope: "user:bot",                          doc: "Allows client’s bot to act as this user.";
     UserEdit,                       scope: "user:edit",                         doc: "Manage a user object.";
     UserEditBroadcast,              scope: "user:edit:broadcast",               doc: "Edit your channel's broadcast configuration, including extension configuration. (This scope implies user:read:broadcast capability.)";
@@ -227,12 +254,145 @@ scope_impls!(
 impl Scope {
     /// Get the scope as a [validator](Validator).
     pub const fn to_validator(self) -> Validator { Validator::scope(self) }
+
+    /// Parse a list of scopes separated by spaces, `+` or `%20`, like the `scope` parameter in a redirect url.
+    ///
+    /// Unknown scopes are parsed as [`Scope::Other`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::Scope;
+    ///
+    /// let scopes = Scope::parse_list("chat:read+chat:edit%20custom:scope");
+    /// assert_eq!(
+    ///     scopes,
+    ///     [
+    ///         Scope::ChatRead,
+    ///         Scope::ChatEdit,
+    ///         Scope::Other("custom:scope".into())
+    ///     ]
+    /// );
+    /// ```
+    pub fn parse_list(s: &str) -> Vec<Scope> {
+        s.replace("%20", " ")
+            .split([' ', '+'])
+            .filter(|s| !s.is_empty())
+            .map(|s| Scope::parse(s.to_owned()))
+            .collect()
+    }
+
+    /// Parse a scope, returning an error for unknown scopes instead of [`Scope::Other`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::Scope;
+    ///
+    /// assert_eq!(Scope::parse_strict("chat:read"), Ok(Scope::ChatRead));
+    /// assert!(Scope::parse_strict("custom:scope").is_err());
+    /// ```
+    pub fn parse_strict(s: &str) -> Result<Scope, ScopeParseError> {
+        match Scope::parse(s.to_owned()) {
+            Scope::Other(s) => Err(ScopeParseError(s.into_owned())),
+            scope => Ok(scope),
+        }
+    }
+
+    /// Get all analytics scopes, `analytics:*`
+    pub fn analytics_all() -> Vec<Scope> { Self::with_prefix("analytics:") }
+
+    /// Get all channel scopes, `channel:*`
+    pub fn channel_all() -> Vec<Scope> { Self::with_prefix("channel:") }
+
+    /// Get the scopes for reading and sending chat messages, both over IRC and the API.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{tokens::UserTokenBuilder, Scope};
+    /// let redirect_url = url::Url::parse("http://localhost/twitch/register")?;
+    /// let builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url)
+    ///     .set_scopes(Scope::chat_read_write());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn chat_read_write() -> Vec<Scope> {
+        vec![
+            Scope::ChatEdit,
+            Scope::ChatRead,
+            Scope::UserReadChat,
+            Scope::UserWriteChat,
+        ]
+    }
+
+    /// Get all moderation scopes, `moderator:*` as well as `moderation:read` and `channel:moderate`
+    pub fn moderation_all() -> Vec<Scope> {
+        let mut scopes = vec![Scope::ChannelModerate, Scope::ModerationRead];
+        scopes.extend(Self::with_prefix("moderator:"));
+        scopes
+    }
+
+    /// Get all user scopes, `user:*`
+    pub fn user_all() -> Vec<Scope> { Self::with_prefix("user:") }
+
+    /// Get the scopes for reading and sending whispers.
+    pub fn whispers_all() -> Vec<Scope> {
+        vec![
+            Scope::UserManageWhispers,
+            Scope::WhispersEdit,
+            Scope::WhispersRead,
+        ]
+    }
+
+    fn with_prefix(prefix: &str) -> Vec<Scope> {
+        Scope::all()
+            .into_iter()
+            .filter(|scope| scope.as_str().starts_with(prefix))
+            .collect()
+    }
+
+    /// Get all [scopes](Scope) that can be requested for the given token type.
+    ///
+    /// Scopes only apply to user tokens, an [app access token](crate::AppAccessToken) is not granted any scopes.
+    /// Use [`Scope::description`] to describe each scope, for example on a consent screen.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{tokens::BearerTokenType, Scope};
+    ///
+    /// for scope in Scope::grantable_for(BearerTokenType::UserToken) {
+    ///     println!("{scope}: {}", scope.description());
+    /// }
+    /// ```
+    pub fn grantable_for(token_type: crate::tokens::BearerTokenType) -> Vec<Scope> {
+        match token_type {
+            crate::tokens::BearerTokenType::UserToken => Scope::all(),
+            crate::tokens::BearerTokenType::AppAccessToken => vec![],
+        }
+    }
 }
 
 impl std::borrow::Borrow<str> for Scope {
     fn borrow(&self) -> &str { self.as_str() }
 }
 
+// Hash the string, so that it matches the hash of the `str` from `Borrow<str>`.
+impl std::hash::Hash for Scope {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) { self.as_str().hash(state) }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Ok(Scope::parse(s.to_owned())) }
+}
+
+/// Error returned by [`Scope::parse_strict`] for an unknown scope
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown scope: {0}")]
+pub struct ScopeParseError(pub String);
+
 impl From<String> for Scope {
     fn from(s: String) -> Self { Scope::parse(s) }
 }
@@ -244,6 +404,41 @@ impl From<Scope> for String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[test]
+    fn description() {
+        assert_eq!(
+            Scope::ChannelReadSubscriptions.description(),
+            "View a list of all subscribers to a channel and check if a user is subscribed to a channel."
+        );
+        assert_eq!(Scope::parse("custom_scope").description(), "unknown scope");
+    }
+
+    #[test]
+    fn parse_strict() {
+        for scope in Scope::all() {
+            assert_eq!(Scope::parse_strict(&scope.to_string()), Ok(scope));
+        }
+        assert_eq!(
+            Scope::parse_strict("custom_scope"),
+            Err(ScopeParseError("custom_scope".to_owned()))
+        );
+        assert_eq!(
+            ScopeParseError("custom_scope".to_owned()).to_string(),
+            "unknown scope: custom_scope"
+        );
+    }
+
+    #[test]
+    fn hash_set_str_lookup() {
+        let scopes: std::collections::HashSet<Scope> =
+            [Scope::ChatRead, Scope::parse("custom_scope")]
+                .into_iter()
+                .collect();
+        assert!(scopes.contains("chat:read"));
+        assert!(scopes.contains("custom_scope"));
+        assert!(!scopes.contains("chat:edit"));
+    }
+
     #[test]
     fn custom_scope() {
         assert_eq!(
@@ -259,6 +454,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn groups() {
+        assert!(Scope::analytics_all().contains(&Scope::AnalyticsReadGames));
+        assert!(Scope::channel_all().contains(&Scope::ChannelReadPolls));
+        assert!(!Scope::channel_all().contains(&Scope::ChatRead));
+        assert!(Scope::moderation_all().contains(&Scope::ModeratorManageBannedUsers));
+        assert!(Scope::moderation_all().contains(&Scope::ChannelModerate));
+        assert!(Scope::user_all().contains(&Scope::UserReadEmail));
+        #[allow(deprecated)]
+        {
+            assert!(!Scope::user_all().contains(&Scope::UserEditFollows));
+        }
+    }
+
+    #[test]
+    fn grantable_for() {
+        use crate::tokens::BearerTokenType;
+        assert_eq!(
+            Scope::grantable_for(BearerTokenType::UserToken),
+            Scope::all()
+        );
+        assert!(Scope::grantable_for(BearerTokenType::AppAccessToken).is_empty());
+    }
+
+    #[test]
+    fn from_str() {
+        for scope in Scope::all() {
+            assert_eq!(scope, scope.to_string().parse().unwrap())
+        }
+        assert_eq!(
+            Scope::Other(Cow::from("custom_scope")),
+            "custom_scope".parse().unwrap()
+        )
+    }
+
+    #[test]
+    fn parse_list() {
+        assert_eq!(
+            Scope::parse_list("chat:read chat:edit"),
+            [Scope::ChatRead, Scope::ChatEdit]
+        );
+        assert_eq!(
+            Scope::parse_list("chat:read+chat:edit%20whispers:read"),
+            [Scope::ChatRead, Scope::ChatEdit, Scope::WhispersRead]
+        );
+        assert!(Scope::parse_list("").is_empty());
+    }
+
     #[test]
     #[allow(deprecated)]
     fn no_deprecated() {
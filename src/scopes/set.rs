@@ -0,0 +1,103 @@
+//! Set of scopes, for membership checks and comparing scopes.
+use std::collections::HashSet;
+
+use super::Scope;
+
+/// A set of [scopes](Scope).
+///
+/// # Examples
+///
+/// ```rust
+/// use twitch_oauth2::{Scope, ScopeSet};
+///
+/// let granted: ScopeSet = vec![Scope::ChatRead].into_iter().collect();
+/// let required: ScopeSet = vec![Scope::ChatRead, Scope::ChatEdit].into_iter().collect();
+/// // Scopes that need to be requested in a re-authentication
+/// let missing = required.difference(&granted);
+/// assert!(missing.contains(&Scope::ChatEdit));
+/// assert_eq!(missing.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopeSet {
+    scopes: HashSet<Scope>,
+}
+
+impl ScopeSet {
+    /// Create an empty [`ScopeSet`]
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns whether or not the set contains the scope
+    pub fn contains(&self, scope: &Scope) -> bool { self.scopes.contains(scope) }
+
+    /// Add a scope to the set, returns false if the scope was already in the set
+    pub fn insert(&mut self, scope: Scope) -> bool { self.scopes.insert(scope) }
+
+    /// Remove a scope from the set, returns false if the scope was not in the set
+    pub fn remove(&mut self, scope: &Scope) -> bool { self.scopes.remove(scope) }
+
+    /// Amount of scopes in the set
+    pub fn len(&self) -> usize { self.scopes.len() }
+
+    /// Returns whether or not the set is empty
+    pub fn is_empty(&self) -> bool { self.scopes.is_empty() }
+
+    /// Iterate over the scopes in the set, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &Scope> { self.scopes.iter() }
+
+    /// Get the scopes in this set that are not in `other`
+    pub fn difference(&self, other: &ScopeSet) -> ScopeSet {
+        self.scopes.difference(&other.scopes).cloned().collect()
+    }
+
+    /// Get the scopes that are in both this set and `other`
+    pub fn intersection(&self, other: &ScopeSet) -> ScopeSet {
+        self.scopes.intersection(&other.scopes).cloned().collect()
+    }
+
+    /// Returns whether or not all scopes in this set are in `other`
+    pub fn is_subset(&self, other: &ScopeSet) -> bool { self.scopes.is_subset(&other.scopes) }
+}
+
+impl FromIterator<Scope> for ScopeSet {
+    fn from_iter<T: IntoIterator<Item = Scope>>(iter: T) -> Self {
+        ScopeSet {
+            scopes: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<Scope> for ScopeSet {
+    fn extend<T: IntoIterator<Item = Scope>>(&mut self, iter: T) { self.scopes.extend(iter) }
+}
+
+impl IntoIterator for ScopeSet {
+    type IntoIter = std::collections::hash_set::IntoIter<Scope>;
+    type Item = Scope;
+
+    fn into_iter(self) -> Self::IntoIter { self.scopes.into_iter() }
+}
+
+impl From<Vec<Scope>> for ScopeSet {
+    fn from(scopes: Vec<Scope>) -> Self { scopes.into_iter().collect() }
+}
+
+impl From<&[Scope]> for ScopeSet {
+    fn from(scopes: &[Scope]) -> Self { scopes.iter().cloned().collect() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_operations() {
+        let a = ScopeSet::from(vec![Scope::ChatRead, Scope::ChatEdit, Scope::ChatRead]);
+        let b = ScopeSet::from(&[Scope::ChatEdit, Scope::WhispersRead][..]);
+        assert_eq!(a.len(), 2);
+        assert_eq!(a.difference(&b), ScopeSet::from(vec![Scope::ChatRead]));
+        assert_eq!(a.intersection(&b), ScopeSet::from(vec![Scope::ChatEdit]));
+        assert!(a.intersection(&b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+        assert!(ScopeSet::new().is_empty());
+    }
+}
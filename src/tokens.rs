@@ -1,12 +1,29 @@
 //! Twitch token types
 
 mod app_access_token;
+#[cfg(feature = "client")]
+mod auto_refresh_token;
+#[cfg(feature = "client")]
+mod dyn_twitch_token;
 pub mod errors;
+#[cfg(feature = "local-server")]
+mod local_server;
+#[cfg(feature = "client")]
+mod token_store;
 mod user_token;
 
-pub use app_access_token::AppAccessToken;
+pub use app_access_token::{AppAccessToken, AppAccessTokenBuilder};
+#[cfg(feature = "client")]
+pub use auto_refresh_token::{AutoRefreshToken, TokenReceiver};
+#[cfg(feature = "client")]
+pub use dyn_twitch_token::DynTwitchToken;
+#[cfg(feature = "client")]
+pub use token_store::{FileTokenStore, FileTokenStoreError, MemoryTokenStore, TokenStore};
 use twitch_types::{UserId, UserIdRef, UserName, UserNameRef};
-pub use user_token::{ImplicitUserTokenBuilder, UserToken, UserTokenBuilder};
+pub use user_token::{
+    DeviceUserTokenBuilder, ImplicitRedirect, ImplicitUserTokenBuilder, RefreshOutcome,
+    UncheckedUserTokenBuilder, UserToken, UserTokenBuilder,
+};
 
 #[cfg(feature = "client")]
 use crate::client::Client;
@@ -36,6 +53,10 @@ pub enum BearerTokenType {
 pub trait TwitchToken {
     /// Get the type of token.
     fn token_type() -> BearerTokenType;
+    /// Get the type of this token.
+    ///
+    /// Same as [`token_type`](TwitchToken::token_type), but can be called on a value.
+    fn token_type_of(&self) -> BearerTokenType { Self::token_type() }
     /// Client ID associated with the token. Twitch requires this in all helix API calls
     fn client_id(&self) -> &ClientId;
     /// Get the [AccessToken] for authenticating
@@ -50,6 +71,26 @@ pub trait TwitchToken {
     /// println!("token: {}", user_token.token().secret());
     /// ```
     fn token(&self) -> &AccessToken;
+    /// Get the token as a bearer string, `Bearer <token>`, as used in the `Authorization` header of helix requests.
+    fn as_bearer(&self) -> String { format!("Bearer {}", self.token().secret()) }
+    /// Get the `Authorization` header value for this token, marked as [sensitive](http::HeaderValue::set_sensitive).
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// # use twitch_oauth2::UserToken;
+    /// # fn t() -> UserToken {todo!()}
+    /// # let user_token = t();
+    /// use twitch_oauth2::TwitchToken;
+    /// let mut headers = http::HeaderMap::new();
+    /// headers.insert(http::header::AUTHORIZATION, user_token.header_value()?);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn header_value(&self) -> Result<http::HeaderValue, http::header::InvalidHeaderValue> {
+        let mut value = http::HeaderValue::from_str(&self.as_bearer())?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
     /// Get the username associated to this token
     fn login(&self) -> Option<&UserNameRef>;
     /// Get the user id associated to this token
@@ -66,6 +107,19 @@ pub trait TwitchToken {
     /// Get current lifetime of token.
     fn expires_in(&self) -> std::time::Duration;
 
+    /// Returns whether or not the token never expires. Defaults to false.
+    ///
+    /// When true, [`expires_in`](TwitchToken::expires_in) is meaningless and [`expires_at`](TwitchToken::expires_at) is `None`.
+    fn never_expires(&self) -> bool { false }
+
+    /// Get the point in time when the token expires, or `None` if the token never expires.
+    fn expires_at(&self) -> Option<std::time::SystemTime> {
+        if self.never_expires() {
+            return None;
+        }
+        std::time::SystemTime::now().checked_add(self.expires_in())
+    }
+
     /// Returns whether or not the token is expired.
     ///
     /// ```rust, no_run
@@ -84,10 +138,79 @@ pub trait TwitchToken {
         let exp = self.expires_in();
         exp.as_secs() == 0 && exp.as_nanos() == 0
     }
+    /// Returns whether or not the token expires within the given duration.
+    ///
+    /// Useful for refreshing a token before it expires. Always false for tokens that never expire.
+    fn expires_within(&self, duration: std::time::Duration) -> bool {
+        !self.never_expires() && self.expires_in() <= duration
+    }
+    /// Get the lifetime the token was granted with, or `None` if it's unknown or the token never expires.
+    fn initial_expires_in(&self) -> Option<std::time::Duration> { None }
+    /// Get the fraction of the [initial lifetime](TwitchToken::initial_expires_in) that remains, between `0.0` and `1.0`.
+    ///
+    /// Returns `None` if the initial lifetime is unknown or the token never expires. Useful for monitoring how fresh tokens are.
+    fn lifetime_fraction_remaining(&self) -> Option<f64> {
+        let initial = self.initial_expires_in()?;
+        if initial.is_zero() {
+            return Some(0.0);
+        }
+        Some((self.expires_in().as_secs_f64() / initial.as_secs_f64()).clamp(0.0, 1.0))
+    }
+    /// Get how long ago this token was created or last refreshed, or `None` if it's unknown.
+    ///
+    /// This is the age of the value, not of the token at twitch. Unlike [`expires_in`](TwitchToken::expires_in), this does not depend on the lifetime of the token.
+    fn age(&self) -> Option<std::time::Duration> { None }
     /// Retrieve scopes attached to the token
     fn scopes(&self) -> &[Scope];
+    /// Get the scopes attached to the token as a [`ScopeSet`](crate::ScopeSet), for comparing with other scopes.
+    fn scope_set(&self) -> crate::ScopeSet { self.scopes().into() }
+    /// Returns whether or not the token has all the given scopes.
+    ///
+    /// Known [scopes](Scope) can be used in const context, so required scopes can be declared as a constant.
+    ///
+    /// ```rust, no_run
+    /// # use twitch_oauth2::UserToken;
+    /// # fn t() -> UserToken {todo!()}
+    /// # let user_token = t();
+    /// use twitch_oauth2::{Scope, TwitchToken};
+    /// const REQUIRED: &[Scope] = &[Scope::ChatEdit, Scope::ChatRead];
+    /// if !user_token.satisfies(REQUIRED) {
+    ///     println!("token is missing required scopes");
+    /// }
+    /// ```
+    fn satisfies(&self, required: &[Scope]) -> bool { self.has_scopes(required) }
+
+    /// Returns whether or not the token has the given scope.
+    fn has_scope(&self, scope: &Scope) -> bool { self.scopes().contains(scope) }
+
+    /// Returns whether or not the token has all the given scopes.
+    fn has_scopes(&self, scopes: &[Scope]) -> bool {
+        scopes.iter().all(|scope| self.has_scope(scope))
+    }
+
+    /// Get the scopes in `required` that are missing from the token.
+    ///
+    /// ```rust, no_run
+    /// # use twitch_oauth2::UserToken;
+    /// # fn t() -> UserToken {todo!()}
+    /// # let user_token = t();
+    /// use twitch_oauth2::{Scope, TwitchToken};
+    /// let missing = user_token.missing_scopes(&[Scope::ModeratorManageBannedUsers]);
+    /// if !missing.is_empty() {
+    ///     println!("token is missing scopes: {missing:?}");
+    /// }
+    /// ```
+    fn missing_scopes(&self, required: &[Scope]) -> Vec<Scope> {
+        required
+            .iter()
+            .filter(|scope| !self.has_scope(scope))
+            .cloned()
+            .collect()
+    }
     /// Validate this token. Should be checked on regularly, according to <https://dev.twitch.tv/docs/authentication/validate-tokens/>
     ///
+    /// If twitch rejects the token after its [lifetime](TwitchToken::expires_in) has passed, [`ValidationError::Expired`] is returned instead of [`ValidationError::NotAuthorized`].
+    ///
     /// # Note
     ///
     /// This will not mutate any current data in the [TwitchToken]
@@ -101,10 +224,19 @@ pub trait TwitchToken {
         C: Client,
     {
         let token = &self.token();
-        token.validate_token(http_client).await
+        match token.validate_token(http_client).await {
+            // Twitch rejects expired and invalid tokens with the same message, so use the expiry we know of.
+            Err(ValidationError::NotAuthorized) if self.is_elapsed() => {
+                Err(ValidationError::Expired)
+            }
+            validated => validated,
+        }
     }
 
     /// Revoke the token. See <https://dev.twitch.tv/docs/authentication/revoke-tokens>
+    ///
+    /// This consumes the token, as it can not be used after being revoked.
+    /// See [`AccessToken::revoke_token`](crate::types::AccessTokenRef::revoke_token) for how errors from twitch are returned.
     #[cfg(feature = "client")]
     async fn revoke_token<'a, C>(
         self,
@@ -146,6 +278,14 @@ impl<T: TwitchToken + Send> TwitchToken for Box<T> {
 
     fn expires_in(&self) -> std::time::Duration { (**self).expires_in() }
 
+    fn never_expires(&self) -> bool { (**self).never_expires() }
+
+    fn expires_at(&self) -> Option<std::time::SystemTime> { (**self).expires_at() }
+
+    fn initial_expires_in(&self) -> Option<std::time::Duration> { (**self).initial_expires_in() }
+
+    fn age(&self) -> Option<std::time::Duration> { (**self).age() }
+
     fn scopes(&self) -> &[Scope] { (**self).scopes() }
 }
 
@@ -163,6 +303,8 @@ pub struct ValidatedToken {
     /// Scopes attached to the token.
     pub scopes: Option<Vec<Scope>>,
     /// Lifetime of the token
+    ///
+    /// Twitch returns an `expires_in` of `0` for legacy tokens that never expire, which is parsed as `None`.
     #[serde(deserialize_with = "expires_in")]
     pub expires_in: Option<std::time::Duration>,
 }
@@ -180,6 +322,15 @@ fn expires_in<'a, D: serde::de::Deserializer<'a>>(
 }
 
 impl ValidatedToken {
+    /// Type of the token, app access tokens are validated without a [`login`](ValidatedToken::login) or [`user_id`](ValidatedToken::user_id).
+    pub fn token_type(&self) -> BearerTokenType {
+        if self.user_id.is_some() {
+            BearerTokenType::UserToken
+        } else {
+            BearerTokenType::AppAccessToken
+        }
+    }
+
     /// Assemble a a validated token from a response.
     ///
     /// Get the request that generates this response with [`AccessToken::validate_token_request`][crate::types::AccessTokenRef::validate_token_request]
@@ -192,7 +343,7 @@ impl ValidatedToken {
                 RequestParseError::TwitchError(TwitchTokenErrorResponse { status, .. })
                     if status == http::StatusCode::UNAUTHORIZED =>
                 {
-                    Err(ValidationError::NotAuthorized)
+                    Err(ValidationError::unauthorized(response))
                 }
                 err => Err(err.into()),
             },
@@ -206,6 +357,66 @@ mod tests {
 
     use super::errors::ValidationError;
 
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn validate_tokens() {
+        use crate::{client::MockClient, AccessToken};
+
+        let tokens: Vec<_> = (0..5)
+            .map(|i| AccessToken::new(format!("token{i}")))
+            .collect();
+
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response("random_client", "justintv", "1337", &[], 3600),
+        );
+        let results = crate::validate_tokens(&client, &tokens, 2).await;
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|r| r.is_ok()));
+
+        let results = crate::validate_tokens(&MockClient::new(), &tokens, 2).await;
+        assert_eq!(results.len(), 5);
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, Err(ValidationError::Request(_)))));
+
+        assert!(crate::validate_tokens(&client, &[], 0).await.is_empty());
+    }
+
+    #[test]
+    fn bearer() {
+        use super::{TwitchToken, UserToken};
+
+        let token = UserToken::unchecked_builder("token", "random_client", "user", "1234").build();
+        assert_eq!(token.as_bearer(), "Bearer token");
+        let value = token.header_value().unwrap();
+        assert_eq!(value, "Bearer token");
+        assert!(value.is_sensitive());
+        let access_token: &crate::AccessToken = token.as_ref();
+        assert_eq!(access_token.secret(), "token");
+    }
+
+    #[test]
+    fn never_expires() {
+        use super::{TwitchToken, UserToken};
+
+        let token: Box<UserToken> = Box::new(
+            UserToken::unchecked_builder("token", "random_client", "user", "1234").build(),
+        );
+        assert!(TwitchToken::never_expires(&token));
+        assert!(token.expires_at().is_none());
+        assert!(!token.expires_within(std::time::Duration::MAX));
+
+        let token: Box<UserToken> = Box::new(
+            UserToken::unchecked_builder("token", "random_client", "user", "1234")
+                .expires_in(std::time::Duration::from_secs(u64::MAX))
+                .build(),
+        );
+        assert!(!TwitchToken::never_expires(&token));
+        assert!(token.expires_within(std::time::Duration::MAX));
+    }
+
     #[test]
     fn validated_token() {
         let body = br#"
@@ -223,6 +434,21 @@ mod tests {
         ValidatedToken::from_response(&response).unwrap();
     }
 
+    #[test]
+    fn validated_app_access_token() {
+        let body = br#"
+        {
+            "client_id": "wbmytr93xzw8zbg0p1izqyzzc5mbiz",
+            "scopes": [],
+            "expires_in": 5520838
+        }
+        "#;
+        let response = http::Response::builder().status(200).body(body).unwrap();
+        let token = ValidatedToken::from_response(&response).unwrap();
+        assert!(token.login.is_none());
+        assert_eq!(token.token_type(), super::BearerTokenType::AppAccessToken);
+    }
+
     #[test]
     fn validated_non_expiring_token() {
         let body = br#"
@@ -241,6 +467,110 @@ mod tests {
         assert!(token.expires_in.is_none());
     }
 
+    #[test]
+    fn validated_unauthorized() {
+        let body = br#"{"status": 401, "message": "invalid access token"}"#;
+        let response = http::Response::builder().status(401).body(body).unwrap();
+        let error = ValidatedToken::from_response(&response).unwrap_err();
+        assert!(matches!(error, ValidationError::NotAuthorized));
+
+        let body = br#"{"status": 401, "message": "Access token has expired"}"#;
+        let response = http::Response::builder().status(401).body(body).unwrap();
+        let error = ValidatedToken::from_response(&response).unwrap_err();
+        assert!(matches!(error, ValidationError::Expired));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn validate_elapsed_token() {
+        use super::TwitchToken;
+        use crate::{client::MockClient, UserToken};
+
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::error_response(http::StatusCode::UNAUTHORIZED, "invalid access token"),
+        );
+        let token = |expires_in| {
+            UserToken::unchecked_builder("token", "random_client", "user", "1234")
+                .expires_in(expires_in)
+                .build()
+        };
+        let error = token(std::time::Duration::ZERO)
+            .validate_token(&client)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ValidationError::Expired));
+
+        let error = token(std::time::Duration::from_secs(3600))
+            .validate_token(&client)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ValidationError::NotAuthorized));
+    }
+
+    #[test]
+    fn validated_unexpected_response() {
+        use crate::RequestParseError;
+
+        let response = http::Response::builder()
+            .status(502)
+            .body(b"<html>Bad Gateway</html>")
+            .unwrap();
+        match ValidatedToken::from_response(&response).unwrap_err() {
+            ValidationError::RequestParseError(RequestParseError::UnexpectedResponse {
+                status,
+                body,
+            }) => {
+                assert_eq!(status, 502);
+                assert_eq!(body, "<html>Bad Gateway</html>");
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+
+        let response = http::Response::builder()
+            .status(500)
+            .body("a".repeat(1000))
+            .unwrap();
+        match ValidatedToken::from_response(&response).unwrap_err() {
+            ValidationError::RequestParseError(RequestParseError::UnexpectedResponse {
+                body,
+                ..
+            }) => assert!(body.len() < 300),
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn validated_invalid_body() {
+        use crate::RequestParseError;
+
+        let response = http::Response::builder()
+            .status(200)
+            .body(b"<html>Twitch</html>")
+            .unwrap();
+        match ValidatedToken::from_response(&response).unwrap_err() {
+            ValidationError::RequestParseError(RequestParseError::InvalidBody {
+                status,
+                body,
+                ..
+            }) => {
+                assert_eq!(status, 200);
+                assert_eq!(body, "<html>Twitch</html>");
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+
+        let response = http::Response::builder()
+            .status(200)
+            .body(br#"{"access_token":1}"#)
+            .unwrap();
+        assert!(matches!(
+            crate::id::TwitchTokenResponse::from_response(&response),
+            Err(RequestParseError::InvalidBody { .. })
+        ));
+    }
+
     #[test]
     fn validated_error_response() {
         let body = br#"
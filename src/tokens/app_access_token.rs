@@ -46,6 +46,29 @@ impl std::fmt::Debug for AppAccessToken {
     }
 }
 
+/// Overwrites the access token, refresh token and client secret with zeroes.
+///
+/// The secrets are also zeroized when they are dropped, so this is only needed to wipe them while the token is still alive.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for AppAccessToken {
+    fn zeroize(&mut self) {
+        use zeroize::Zeroize as _;
+
+        self.access_token.zeroize();
+        if let Some(refresh_token) = &mut self.refresh_token {
+            refresh_token.zeroize();
+        }
+        self.client_secret.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for AppAccessToken {}
+
+impl AsRef<AccessToken> for AppAccessToken {
+    fn as_ref(&self) -> &AccessToken { &self.access_token }
+}
+
 #[cfg_attr(feature = "client", async_trait::async_trait)]
 impl TwitchToken for AppAccessToken {
     fn token_type() -> super::BearerTokenType { super::BearerTokenType::AppAccessToken }
@@ -66,17 +89,17 @@ impl TwitchToken for AppAccessToken {
     where
         C: Client,
     {
-        let (access_token, expires_in, refresh_token) =
-            if let Some(token) = self.refresh_token.take() {
-                token
-                    .refresh_token(http_client, &self.client_id, &self.client_secret)
-                    .await?
-            } else {
-                return Err(RefreshTokenError::NoRefreshToken);
-            };
+        let (access_token, expires_in, refresh_token) = if let Some(token) = &self.refresh_token {
+            token
+                .refresh_token(http_client, &self.client_id, &self.client_secret)
+                .await?
+        } else {
+            return Err(RefreshTokenError::NoRefreshToken);
+        };
         self.access_token = access_token;
         self.expires_in = expires_in;
         self.refresh_token = refresh_token;
+        self.struct_created = std::time::Instant::now();
         Ok(())
     }
 
@@ -86,6 +109,10 @@ impl TwitchToken for AppAccessToken {
             .unwrap_or_default()
     }
 
+    fn initial_expires_in(&self) -> Option<std::time::Duration> { Some(self.expires_in) }
+
+    fn age(&self) -> Option<std::time::Duration> { Some(self.struct_created.elapsed()) }
+
     fn scopes(&self) -> &[Scope] { self.scopes.as_slice() }
 }
 
@@ -118,6 +145,8 @@ impl AppAccessToken {
     }
 
     /// Assemble token and validate it. Retrieves [`client_id`](TwitchToken::client_id) and [`scopes`](TwitchToken::scopes).
+    ///
+    /// App access tokens are validated without a `login` or `user_id`, a user token fails with [`ValidationError::InvalidToken`].
     #[cfg(feature = "client")]
     pub async fn from_existing<RE, C>(
         http_client: &C,
@@ -130,7 +159,7 @@ impl AppAccessToken {
     {
         let token = access_token;
         let validated = token.validate_token(http_client).await?;
-        if validated.user_id.is_some() {
+        if validated.token_type() != super::BearerTokenType::AppAccessToken {
             return Err(ValidationError::InvalidToken(
                 "expected an app access token, got a user access token",
             ));
@@ -184,6 +213,14 @@ impl AppAccessToken {
     /// # Ok::<(), Box<dyn std::error::Error>>(())};
     /// ```
     #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(client_id = %client_id, scopes = ?scopes, status),
+            err(level = "warn")
+        )
+    )]
     pub async fn get_app_access_token<C>(
         http_client: &C,
         client_id: ClientId,
@@ -193,17 +230,72 @@ impl AppAccessToken {
     where
         C: Client,
     {
-        let req = Self::get_app_access_token_request(&client_id, &client_secret, scopes);
-
-        let resp = http_client
-            .req(req)
+        AppAccessTokenBuilder::new(client_id, client_secret)
+            .set_scopes(scopes)
+            .max_retries(0)
+            .get_app_access_token(http_client, |_| async {})
             .await
-            .map_err(AppAccessTokenError::Request)?;
+    }
 
-        let response = crate::id::TwitchTokenResponse::from_response(&resp)?;
-        let app_access = AppAccessToken::from_response(response, client_id, client_secret);
+    /// Get a new app access token if this one expires within a minute, otherwise keep the current token.
+    ///
+    /// App access tokens from the client credentials flow can not be refreshed with [`TwitchToken::refresh_token`],
+    /// as they have no refresh token. Instead, a new token has to be issued with [`AppAccessToken::get_app_access_token`], which this does.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use twitch_oauth2::{AppAccessToken, TwitchToken};
+    /// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .build()?;
+    /// # );
+    /// # fn t() -> AppAccessToken {todo!()}
+    /// let mut token: AppAccessToken = t(); // A cached token
+    /// token.get_or_refresh(&client).await?;
+    /// println!("token: {:?}", token.token());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())};
+    /// ```
+    #[cfg(feature = "client")]
+    pub async fn get_or_refresh<C>(
+        &mut self,
+        http_client: &C,
+    ) -> Result<(), AppAccessTokenError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        if self.expires_in() > std::time::Duration::from_secs(60) {
+            return Ok(());
+        }
+        self.reissue(http_client).await?;
+        Ok(())
+    }
 
-        Ok(app_access)
+    /// Issue a new app access token with the same client id, client secret and scopes, replacing this token.
+    ///
+    /// This is the app access token equivalent of [`refresh_token`](TwitchToken::refresh_token) on a [`UserToken`](super::UserToken).
+    /// Returns `true` if twitch issued a different access token. The old token is not revoked and may still be valid until it expires.
+    ///
+    /// See [`get_or_refresh`](AppAccessToken::get_or_refresh) to only issue a new token when the current one is about to expire.
+    #[cfg(feature = "client")]
+    pub async fn reissue<C>(
+        &mut self,
+        http_client: &C,
+    ) -> Result<bool, AppAccessTokenError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let token = AppAccessToken::get_app_access_token(
+            http_client,
+            self.client_id.clone(),
+            self.client_secret.clone(),
+            self.scopes.clone(),
+        )
+        .await?;
+        let changed = token.access_token.secret() != self.access_token.secret();
+        *self = token;
+        Ok(changed)
     }
 
     /// Get the request for getting an app access token.
@@ -214,25 +306,514 @@ impl AppAccessToken {
         client_secret: &ClientSecretRef,
         scopes: Vec<Scope>,
     ) -> http::Request<Vec<u8>> {
-        use http::{HeaderMap, Method};
-        use std::collections::HashMap;
-        let scope: String = scopes
-            .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>()
-            .join(" ");
-        let mut params = HashMap::new();
-        params.insert("client_id", client_id.as_str());
-        params.insert("client_secret", client_secret.secret());
-        params.insert("grant_type", "client_credentials");
+        app_access_token_request(&crate::TOKEN_URL, client_id, client_secret, &scopes, false)
+    }
+
+    /// Get the request for getting an app access token from [`TwitchUrls::token_url`](crate::TwitchUrls::token_url), see [`get_app_access_token_request`](AppAccessToken::get_app_access_token_request).
+    pub fn get_app_access_token_request_with_urls(
+        client_id: &ClientIdRef,
+        client_secret: &ClientSecretRef,
+        scopes: Vec<Scope>,
+        urls: &crate::TwitchUrls,
+    ) -> http::Request<Vec<u8>> {
+        app_access_token_request(&urls.token_url, client_id, client_secret, &scopes, false)
+    }
+}
+
+/// Construct the request for getting an app access token from `token_url`
+fn app_access_token_request(
+    token_url: &url::Url,
+    client_id: &ClientIdRef,
+    client_secret: &ClientSecretRef,
+    scopes: &[Scope],
+    form_body: bool,
+) -> http::Request<Vec<u8>> {
+    use http::{HeaderMap, Method};
+    use std::collections::HashMap;
+    let scope: String = scopes
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut params = HashMap::new();
+    params.insert("client_id", client_id.as_str());
+    params.insert("client_secret", client_secret.secret());
+    params.insert("grant_type", "client_credentials");
+    if !scopes.is_empty() {
         params.insert("scope", &scope);
+    }
+
+    if form_body {
+        return crate::construct_form_request(token_url, &params, HeaderMap::new(), Method::POST);
+    }
+    crate::construct_request(token_url, &params, HeaderMap::new(), Method::POST, vec![])
+}
 
-        crate::construct_request(
+/// Builder for getting an [`AppAccessToken`] via the [OAuth client credentials flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#client-credentials-grant-flow),
+/// retrying with exponential backoff on transient errors.
+///
+/// Server errors (`5xx`), ratelimits and failed requests are retried, other errors like `400 Bad Request` are returned immediately.
+/// When ratelimited, the wait is at least as long as twitch asks for.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use twitch_oauth2::tokens::AppAccessTokenBuilder;
+/// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::builder()
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()?;
+/// # );
+/// let token = AppAccessTokenBuilder::new("my_client_id", "my_client_secret")
+///     .max_retries(5)
+///     .base_delay(std::time::Duration::from_millis(500))
+///     .get_app_access_token(&client, tokio::time::sleep)
+///     .await?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())};
+/// ```
+#[derive(Debug, Clone)]
+pub struct AppAccessTokenBuilder {
+    client_id: ClientId,
+    client_secret: ClientSecret,
+    scopes: Vec<Scope>,
+    token_url: Option<url::Url>,
+    form_body: bool,
+    max_retries: u32,
+    base_delay: std::time::Duration,
+}
+
+impl AppAccessTokenBuilder {
+    /// Create a [`AppAccessTokenBuilder`]
+    ///
+    /// Defaults to 3 retries, with a base delay of one second.
+    pub fn new(
+        client_id: impl Into<ClientId>,
+        client_secret: impl Into<ClientSecret>,
+    ) -> AppAccessTokenBuilder {
+        AppAccessTokenBuilder {
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scopes: vec![],
+            token_url: None,
+            form_body: false,
+            max_retries: 3,
+            base_delay: std::time::Duration::from_secs(1),
+        }
+    }
+
+    /// Create a [`AppAccessTokenBuilder`] that gets the token from [`TwitchUrls::token_url`](crate::TwitchUrls::token_url), see [`AppAccessTokenBuilder::new`].
+    pub fn new_with_urls(
+        client_id: impl Into<ClientId>,
+        client_secret: impl Into<ClientSecret>,
+        urls: crate::TwitchUrls,
+    ) -> AppAccessTokenBuilder {
+        AppAccessTokenBuilder::new(client_id, client_secret).token_url(urls.token_url)
+    }
+
+    /// Create a [`AppAccessTokenBuilder`] from [`ClientCredentials`](crate::ClientCredentials), see [`AppAccessTokenBuilder::new`].
+    pub fn from_credentials(credentials: crate::ClientCredentials) -> AppAccessTokenBuilder {
+        AppAccessTokenBuilder::new(credentials.client_id, credentials.client_secret)
+    }
+
+    /// Add scopes to the request
+    pub fn set_scopes(mut self, scopes: Vec<Scope>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Use another url than [`TOKEN_URL`](crate::TOKEN_URL) to get the token from, for example a mock server.
+    pub fn token_url(mut self, token_url: url::Url) -> Self {
+        self.token_url = Some(token_url);
+        self
+    }
+
+    /// Send the client credentials as an `application/x-www-form-urlencoded` body, instead of in the query. Defaults to false.
+    ///
+    /// Twitch does not support HTTP Basic authentication for the client credentials, they are always sent as parameters.
+    /// Use the body if a proxy between you and twitch strips the query of `POST` requests, leading to `invalid client` errors.
+    pub fn form_body(mut self, b: bool) -> Self {
+        self.form_body = b;
+        self
+    }
+
+    /// Get the request for getting an app access token.
+    ///
+    /// Parse with [TwitchTokenResponse::from_response](crate::id::TwitchTokenResponse::from_response) and [AppAccessToken::from_response]
+    pub fn request(&self) -> http::Request<Vec<u8>> {
+        app_access_token_request(
+            self.token_url.as_ref().unwrap_or(&crate::TOKEN_URL),
+            &self.client_id,
+            &self.client_secret,
+            &self.scopes,
+            self.form_body,
+        )
+    }
+
+    /// Set how many times to retry after the first attempt failed.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry, doubled on each following retry.
+    pub fn base_delay(mut self, base_delay: std::time::Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Get the app access token, retrying on transient errors.
+    ///
+    /// `sleep` is called with the duration to wait, use the sleep function of your async runtime, like [`tokio::time::sleep`](https://docs.rs/tokio/latest/tokio/time/fn.sleep.html).
+    ///
+    /// Returns the last error when all retries are exhausted.
+    #[cfg(feature = "client")]
+    pub async fn get_app_access_token<C, F, Fut>(
+        self,
+        http_client: &C,
+        sleep: F,
+    ) -> Result<AppAccessToken, AppAccessTokenError<<C as Client>::Error>>
+    where
+        C: Client,
+        F: Fn(std::time::Duration) -> Fut,
+        Fut: std::future::Future<Output = ()>,
+    {
+        let mut attempt = 0;
+        loop {
+            let req = self.request();
+            crate::trace_request(&req);
+            let result = match http_client.req(req).await {
+                Ok(resp) => {
+                    crate::trace_response(&resp);
+                    crate::id::TwitchTokenResponse::from_response(&resp)
+                        .map_err(AppAccessTokenError::RequestParseError)
+                }
+                Err(e) => Err(AppAccessTokenError::Request(e)),
+            };
+            match result {
+                Ok(response) => {
+                    return Ok(AppAccessToken::from_response(
+                        response,
+                        self.client_id,
+                        self.client_secret,
+                    ))
+                }
+                Err(e) if attempt < self.max_retries && is_transient(&e) => {
+                    let backoff = self.base_delay.saturating_mul(2u32.saturating_pow(attempt));
+                    sleep(match e {
+                        AppAccessTokenError::RequestParseError(
+                            crate::RequestParseError::Ratelimited {
+                                retry_after: Some(retry_after),
+                            },
+                        ) => retry_after.max(backoff),
+                        _ => backoff,
+                    })
+                    .await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.with_requested_scopes(&self.scopes)),
+            }
+        }
+    }
+}
+
+/// Check if the error is worth retrying
+#[cfg(feature = "client")]
+fn is_transient<RE: std::error::Error + Send + Sync + 'static>(
+    error: &AppAccessTokenError<RE>,
+) -> bool {
+    match error {
+        AppAccessTokenError::Request(_) => true,
+        AppAccessTokenError::RequestParseError(crate::RequestParseError::Ratelimited {
+            ..
+        }) => true,
+        AppAccessTokenError::RequestParseError(crate::RequestParseError::TwitchError(e)) => {
+            e.status.is_server_error()
+        }
+        AppAccessTokenError::RequestParseError(
+            crate::RequestParseError::Other(status)
+            | crate::RequestParseError::UnexpectedResponse { status, .. },
+        ) => status.is_server_error(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "client")]
+mod tests {
+    use std::{cell::RefCell, time::Duration};
+
+    use super::*;
+    use crate::client::MockClient;
+
+    fn client(status: http::StatusCode) -> MockClient {
+        MockClient::new().with_response(
+            http::Method::POST,
             &crate::TOKEN_URL,
-            &params,
-            HeaderMap::new(),
-            Method::POST,
+            MockClient::error_response(status, "error"),
+        )
+    }
+
+    #[tokio::test]
+    async fn retry_server_error() {
+        let sleeps = RefCell::new(vec![]);
+        let err = AppAccessTokenBuilder::new("client_id", "client_secret")
+            .max_retries(3)
+            .base_delay(Duration::from_secs(1))
+            .get_app_access_token(&client(http::StatusCode::INTERNAL_SERVER_ERROR), |d| {
+                sleeps.borrow_mut().push(d);
+                async {}
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppAccessTokenError::RequestParseError(_)));
+        assert_eq!(
+            *sleeps.borrow(),
+            [
+                Duration::from_secs(1),
+                Duration::from_secs(2),
+                Duration::from_secs(4)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn no_retry_client_error() {
+        let sleeps = RefCell::new(vec![]);
+        AppAccessTokenBuilder::new("client_id", "client_secret")
+            .get_app_access_token(&client(http::StatusCode::BAD_REQUEST), |d| {
+                sleeps.borrow_mut().push(d);
+                async {}
+            })
+            .await
+            .unwrap_err();
+        assert!(sleeps.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn retry_ratelimited() {
+        let sleeps = RefCell::new(vec![]);
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            http::Response::builder()
+                .status(http::StatusCode::TOO_MANY_REQUESTS)
+                .header("Retry-After", "10")
+                .body(vec![])
+                .unwrap(),
+        );
+        let err = AppAccessTokenBuilder::new("client_id", "client_secret")
+            .max_retries(1)
+            .get_app_access_token(&client, |d| {
+                sleeps.borrow_mut().push(d);
+                async {}
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppAccessTokenError::RequestParseError(crate::RequestParseError::Ratelimited {
+                retry_after: Some(d)
+            }) if d == Duration::from_secs(10)
+        ));
+        assert_eq!(*sleeps.borrow(), [Duration::from_secs(10)]);
+    }
+
+    #[tokio::test]
+    async fn get_or_refresh() {
+        let mut token = AppAccessToken::from_existing_unchecked(
+            AccessToken::from("cached"),
+            None,
+            "client_id",
+            "client_secret",
+            None,
+            Some(Duration::from_secs(3600)),
+        );
+        token.get_or_refresh(&MockClient::new()).await.unwrap();
+        assert_eq!(token.access_token.secret(), "cached");
+
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("new", None, &[], 3600),
+        );
+        let mut token = AppAccessToken::from_existing_unchecked(
+            AccessToken::from("expired"),
+            None,
+            "client_id",
+            "client_secret",
+            None,
+            None,
+        );
+        token.get_or_refresh(&client).await.unwrap();
+        assert_eq!(token.access_token.secret(), "new");
+        assert!(!token.is_elapsed());
+    }
+
+    #[tokio::test]
+    async fn reissue() {
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("new", None, &[], 3600),
+        );
+        let mut token = AppAccessToken::from_existing_unchecked(
+            AccessToken::from("cached"),
+            None,
+            "client_id",
+            "client_secret",
+            None,
+            Some(Duration::from_secs(10)),
+        );
+        assert!(token.reissue(&client).await.unwrap());
+        assert_eq!(token.access_token.secret(), "new");
+        assert_eq!(token.expires_in, Duration::from_secs(3600));
+        assert!(!token.reissue(&client).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn get_token() {
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("token", None, &[], 3600),
+        );
+        let token = AppAccessTokenBuilder::new("client_id", "client_secret")
+            .get_app_access_token(&client, |_| async {})
+            .await
+            .unwrap();
+        assert_eq!(token.access_token.secret(), "token");
+
+        let token = AppAccessToken::get_app_access_token(
+            &client,
+            "client_id".into(),
+            "client_secret".into(),
             vec![],
         )
+        .await
+        .unwrap();
+        assert_eq!(token.access_token.secret(), "token");
+    }
+
+    #[tokio::test]
+    async fn token_url() {
+        let token_url = url::Url::parse("http://localhost:8080/auth/token").unwrap();
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &token_url,
+            MockClient::token_response("mock_token", None, &[], 3600),
+        );
+        let builder = AppAccessTokenBuilder::new("client_id", "client_secret")
+            .set_scopes(vec![Scope::ChatRead])
+            .token_url(token_url);
+        let req = builder.request();
+        assert_eq!(req.uri().path(), "/auth/token");
+        assert!(req.uri().query().unwrap().contains("scope=chat%3Aread"));
+        let token = builder
+            .get_app_access_token(&client, |_| async {})
+            .await
+            .unwrap();
+        assert_eq!(token.access_token.secret(), "mock_token");
+    }
+
+    #[tokio::test]
+    async fn invalid_scope() {
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::error_response(http::StatusCode::BAD_REQUEST, "invalid scope requested"),
+        );
+        let err = AppAccessTokenBuilder::new("client_id", "client_secret")
+            .set_scopes(vec![Scope::ChatRead])
+            .get_app_access_token(&client, |_| async {})
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppAccessTokenError::InvalidScope { requested_scopes, .. } if requested_scopes == [Scope::ChatRead]
+        ));
+    }
+
+    #[tokio::test]
+    async fn scopes() {
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("mock_token", None, &[], 3600),
+        );
+        let builder = AppAccessTokenBuilder::new("client_id", "client_secret");
+        assert!(!builder.request().uri().query().unwrap().contains("scope"));
+        let token = builder
+            .get_app_access_token(&client, |_| async {})
+            .await
+            .unwrap();
+        assert!(token.scopes().is_empty());
+
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("mock_token", None, &[Scope::ChatRead], 3600),
+        );
+        let builder = AppAccessTokenBuilder::new("client_id", "client_secret")
+            .set_scopes(vec![Scope::ChatRead, Scope::ChatEdit]);
+        assert!(builder
+            .request()
+            .uri()
+            .query()
+            .unwrap()
+            .contains("scope=chat%3Aread+chat%3Aedit"));
+        let token = builder
+            .get_app_access_token(&client, |_| async {})
+            .await
+            .unwrap();
+        // The granted scopes are used, not the requested ones
+        assert_eq!(token.scopes(), [Scope::ChatRead]);
+    }
+
+    #[tokio::test]
+    async fn from_existing() {
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_app_response("random_client", &[], 3600),
+        );
+        let token = AppAccessToken::from_existing(
+            &client,
+            AccessToken::from("token"),
+            None,
+            ClientSecret::from("secret"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(token.client_id().as_str(), "random_client");
+        assert!(token.login().is_none());
+        assert!(token.user_id().is_none());
+
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response("random_client", "justintv", "1337", &[], 3600),
+        );
+        let err = AppAccessToken::from_existing(
+            &client,
+            AccessToken::from("token"),
+            None,
+            ClientSecret::from("secret"),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ValidationError::InvalidToken(_)));
+    }
+
+    #[test]
+    fn form_body() {
+        let req = AppAccessTokenBuilder::new("client_id", "client_secret")
+            .form_body(true)
+            .request();
+        assert_eq!(req.uri().query(), None);
+        let params: std::collections::HashMap<_, _> = url::form_urlencoded::parse(req.body())
+            .into_owned()
+            .collect();
+        assert_eq!(params["client_secret"], "client_secret");
+        assert_eq!(params["grant_type"], "client_credentials");
     }
 }
@@ -0,0 +1,233 @@
+use std::future::Future;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+use std::time::Duration;
+
+use super::errors::RefreshTokenError;
+use crate::client::Client;
+use crate::tokens::TwitchToken;
+use crate::types::AccessToken;
+
+/// A [token](TwitchToken) that refreshes itself when it's about to expire.
+///
+/// The token is refreshed in [`AutoRefreshToken::token`] if it expires within the [threshold](AutoRefreshToken::threshold), which defaults to 60 seconds.
+/// Tokens that never expire are never refreshed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use twitch_oauth2::{tokens::AutoRefreshToken, UserToken};
+/// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::builder()
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()?;
+/// # );
+/// # fn t() -> UserToken {todo!()}
+/// let token: UserToken = t();
+/// let mut token = AutoRefreshToken::new(token, client);
+/// // Refreshes the token if needed
+/// let access_token = token.token().await?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())};
+/// ```
+#[derive(Debug, Clone)]
+pub struct AutoRefreshToken<T, C> {
+    token: T,
+    http_client: C,
+    threshold: Duration,
+}
+
+impl<T, C> AutoRefreshToken<T, C>
+where
+    T: TwitchToken + Send,
+    C: Client,
+{
+    /// Create an [`AutoRefreshToken`] using the given http client for refreshing.
+    pub fn new(token: T, http_client: C) -> Self {
+        AutoRefreshToken {
+            token,
+            http_client,
+            threshold: Duration::from_secs(60),
+        }
+    }
+
+    /// Set how long before expiring the token should be refreshed.
+    pub fn threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Get the [AccessToken], refreshing the token first if it expires within the threshold.
+    pub async fn token(&mut self) -> Result<&AccessToken, RefreshTokenError<<C as Client>::Error>> {
+        if self.token.expires_within(self.threshold) {
+            self.token.refresh_token(&self.http_client).await?;
+        }
+        Ok(self.token.token())
+    }
+
+    /// Get the wrapped token, without refreshing it.
+    pub fn inner(&self) -> &T { &self.token }
+
+    /// Consume this and return the wrapped token.
+    pub fn into_inner(self) -> T { self.token }
+
+    /// Create a task that keeps the token fresh in the background, and a [`TokenReceiver`] to read the latest token.
+    ///
+    /// The task is a future that sleeps with `sleep` until the token expires within the [threshold](AutoRefreshToken::threshold), refreshes it and publishes it to all receivers.
+    /// Spawn it on the runtime of your choice, like [`tokio::spawn`](https://docs.rs/tokio/*/tokio/fn.spawn.html).
+    ///
+    /// The task completes with `Ok(())` when all receivers have been dropped, or when the token never expires.
+    /// If refreshing fails, the task completes with the error and the receivers keep the last token.
+    /// Dropping the task stops refreshing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use twitch_oauth2::{tokens::AutoRefreshToken, UserToken};
+    /// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .build()?;
+    /// # );
+    /// # fn t() -> UserToken {todo!()}
+    /// let token: UserToken = t();
+    /// let (receiver, task) =
+    ///     AutoRefreshToken::new(token, client).into_refresh_task(tokio::time::sleep);
+    /// tokio::spawn(task);
+    /// // Always a fresh token
+    /// println!("{:?}", receiver.borrow().access_token);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())};
+    /// ```
+    pub fn into_refresh_task<F, Fut>(
+        self,
+        sleep: F,
+    ) -> (
+        TokenReceiver<T>,
+        impl Future<Output = Result<(), RefreshTokenError<<C as Client>::Error>>> + Send,
+    )
+    where
+        T: Clone + Sync,
+        F: Fn(Duration) -> Fut + Send,
+        Fut: Future<Output = ()> + Send,
+    {
+        let AutoRefreshToken {
+            mut token,
+            http_client,
+            threshold,
+        } = self;
+        let shared = Arc::new(RwLock::new(token.clone()));
+        let receiver = TokenReceiver {
+            token: shared.clone(),
+        };
+        let task = async move {
+            while !token.never_expires() {
+                sleep(token.expires_in().saturating_sub(threshold)).await;
+                if Arc::strong_count(&shared) == 1 {
+                    // All receivers are gone, nobody needs the token anymore.
+                    break;
+                }
+                token.refresh_token(&http_client).await?;
+                *shared.write().unwrap_or_else(PoisonError::into_inner) = token.clone();
+            }
+            Ok(())
+        };
+        (receiver, task)
+    }
+}
+
+/// Receiver for the latest token refreshed by the task from [`AutoRefreshToken::into_refresh_task`]
+///
+/// Clone the receiver to share it.
+#[derive(Debug, Clone)]
+pub struct TokenReceiver<T> {
+    token: Arc<RwLock<T>>,
+}
+
+impl<T> TokenReceiver<T> {
+    /// Borrow the latest token.
+    ///
+    /// The token can not be updated while it's borrowed, so don't hold the borrow across an `.await`.
+    pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+        self.token.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twitch_types::{UserId, UserName};
+
+    use super::*;
+    use crate::{ClientId, UserToken};
+
+    fn user_token(expires_in: Option<Duration>) -> UserToken {
+        UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            ClientId::from("random_client"),
+            None,
+            UserName::from("user"),
+            UserId::from("1234"),
+            None,
+            expires_in,
+        )
+    }
+
+    #[tokio::test]
+    async fn never_expiring() {
+        let mut token = AutoRefreshToken::new(user_token(None), crate::client::MockClient::new());
+        assert_eq!(token.token().await.unwrap().secret(), "token");
+    }
+
+    #[tokio::test]
+    async fn refresh_within_threshold() {
+        let mut token = AutoRefreshToken::new(
+            user_token(Some(Duration::from_secs(30))),
+            crate::client::MockClient::new(),
+        );
+        let err = token.token().await.unwrap_err();
+        assert!(matches!(err, RefreshTokenError::NotRefreshable { .. }));
+
+        let mut token = AutoRefreshToken::new(
+            user_token(Some(Duration::from_secs(30))),
+            crate::client::MockClient::new(),
+        )
+        .threshold(Duration::from_secs(10));
+        assert_eq!(token.token().await.unwrap().secret(), "token");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refresh_task() {
+        use crate::client::MockClient;
+
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("new_token", Some("new_refresh"), &[], 3600),
+        );
+        let token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .client_secret("secret")
+            .refresh_token("refresh")
+            .expires_in(Duration::from_secs(30))
+            .build();
+        let (receiver, task) =
+            AutoRefreshToken::new(token, client).into_refresh_task(tokio::time::sleep);
+        assert_eq!(receiver.borrow().token().secret(), "token");
+        let task = tokio::spawn(task);
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(receiver.borrow().token().secret(), "new_token");
+
+        // Dropping the receiver stops the task before the next refresh
+        drop(receiver);
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        assert!(task.is_finished());
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn refresh_task_never_expiring() {
+        let (receiver, task) =
+            AutoRefreshToken::new(user_token(None), crate::client::MockClient::new())
+                .into_refresh_task(|_| async { unreachable!() });
+        task.await.unwrap();
+        assert_eq!(receiver.borrow().access_token.secret(), "token");
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use twitch_types::{UserIdRef, UserNameRef};
+
+use super::errors::RefreshTokenError;
+use super::BearerTokenType;
+use crate::client::{BoxedFuture, Client};
+use crate::tokens::{Scope, TwitchToken};
+use crate::types::{AccessToken, ClientId};
+
+/// Object safe version of [`TwitchToken`], to store different kinds of tokens together.
+///
+/// Implemented for all [tokens](TwitchToken) that are `Send`, `C` is the [`Client`] used for refreshing.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use twitch_oauth2::{tokens::DynTwitchToken, AppAccessToken, UserToken};
+/// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::builder()
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()?;
+/// # );
+/// # fn t() -> (UserToken, AppAccessToken) {todo!()}
+/// let (user_token, app_token) = t();
+/// let mut tokens: Vec<Box<dyn DynTwitchToken<_>>> =
+///     vec![Box::new(user_token), Box::new(app_token)];
+/// for token in &mut tokens {
+///     if token.expires_in().as_secs() < 60 {
+///         token.refresh_token(&client).await?;
+///     }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())};
+/// ```
+pub trait DynTwitchToken<C: Client>: Send {
+    /// Get the type of token.
+    fn token_type(&self) -> BearerTokenType;
+    /// Client ID associated with the token. Twitch requires this in all helix API calls
+    fn client_id(&self) -> &ClientId;
+    /// Get the [AccessToken] for authenticating
+    fn token(&self) -> &AccessToken;
+    /// Get the username associated to this token
+    fn login(&self) -> Option<&UserNameRef>;
+    /// Get the user id associated to this token
+    fn user_id(&self) -> Option<&UserIdRef>;
+    /// Get current lifetime of token.
+    fn expires_in(&self) -> Duration;
+    /// Retrieve scopes attached to the token
+    fn scopes(&self) -> &[Scope];
+    /// Refresh this token, changing the token to a newer one
+    fn refresh_token<'a>(
+        &'a mut self,
+        http_client: &'a C,
+    ) -> BoxedFuture<'a, Result<(), RefreshTokenError<<C as Client>::Error>>>;
+}
+
+impl<T, C> DynTwitchToken<C> for T
+where
+    T: TwitchToken + Send,
+    C: Client,
+{
+    fn token_type(&self) -> BearerTokenType { TwitchToken::token_type_of(self) }
+
+    fn client_id(&self) -> &ClientId { TwitchToken::client_id(self) }
+
+    fn token(&self) -> &AccessToken { TwitchToken::token(self) }
+
+    fn login(&self) -> Option<&UserNameRef> { TwitchToken::login(self) }
+
+    fn user_id(&self) -> Option<&UserIdRef> { TwitchToken::user_id(self) }
+
+    fn expires_in(&self) -> Duration { TwitchToken::expires_in(self) }
+
+    fn scopes(&self) -> &[Scope] { TwitchToken::scopes(self) }
+
+    fn refresh_token<'a>(
+        &'a mut self,
+        http_client: &'a C,
+    ) -> BoxedFuture<'a, Result<(), RefreshTokenError<<C as Client>::Error>>> {
+        TwitchToken::refresh_token(self, http_client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client::MockClient, AppAccessToken, UserToken};
+
+    #[tokio::test]
+    async fn heterogeneous_tokens() {
+        let user_token = UserToken::unchecked_builder("user_token", "client_id", "user", "1234")
+            .scopes(vec![Scope::ChatRead])
+            .build();
+        let app_token = AppAccessToken::from_existing_unchecked(
+            AccessToken::from("app_token"),
+            crate::RefreshToken::from("refresh"),
+            "client_id",
+            "client_secret",
+            None,
+            Some(Duration::from_secs(10)),
+        );
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("new_app_token", None, &[], 3600),
+        );
+
+        assert_eq!(user_token.token_type_of(), BearerTokenType::UserToken);
+        assert_eq!(app_token.token_type_of(), BearerTokenType::AppAccessToken);
+
+        let mut tokens: Vec<Box<dyn DynTwitchToken<MockClient>>> =
+            vec![Box::new(user_token), Box::new(app_token)];
+        assert_eq!(tokens[0].token_type(), BearerTokenType::UserToken);
+        assert_eq!(tokens[0].scopes(), [Scope::ChatRead]);
+        assert_eq!(tokens[1].token_type(), BearerTokenType::AppAccessToken);
+        assert!(tokens[1].login().is_none());
+
+        tokens[1].refresh_token(&client).await.unwrap();
+        assert_eq!(tokens[1].token().secret(), "new_app_token");
+    }
+}
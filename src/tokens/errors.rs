@@ -10,20 +10,149 @@ pub enum AppAccessTokenError<RE: std::error::Error + Send + Sync + 'static> {
     Request(#[source] RE),
     /// could not parse response when getting app access token
     RequestParseError(#[from] crate::RequestParseError),
+    /// twitch rejected a requested scope, requested scopes: {requested_scopes:?}
+    InvalidScope {
+        /// The scopes that were requested
+        requested_scopes: Vec<crate::Scope>,
+        /// The error from twitch
+        #[source]
+        error: crate::id::TwitchTokenErrorResponse,
+    },
+}
+
+#[cfg(feature = "client")]
+impl<RE: std::error::Error + Send + Sync + 'static> AppAccessTokenError<RE> {
+    /// Attach the requested scopes to an `invalid scope` error from twitch
+    pub(crate) fn with_requested_scopes(self, scopes: &[crate::Scope]) -> Self {
+        match self {
+            AppAccessTokenError::RequestParseError(crate::RequestParseError::TwitchError(
+                error,
+            )) if error.kind() == crate::id::TwitchErrorKind::InvalidScope => {
+                AppAccessTokenError::InvalidScope {
+                    requested_scopes: scopes.to_vec(),
+                    error,
+                }
+            }
+            e => e,
+        }
+    }
+}
+
+/// new token is missing scopes of the previous token: {lost:?}
+///
+/// Error for [`UserToken::upgraded_from`](crate::tokens::UserToken::upgraded_from)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+pub struct ScopeDowngrade {
+    /// The new token, which can still be used
+    pub token: Box<crate::tokens::UserToken>,
+    /// Scopes of the previous token that the new token does not have
+    pub lost: Vec<crate::Scope>,
+}
+
+/// Errors for [`ClientCredentials::from_env`](crate::ClientCredentials::from_env) and [`UserTokenBuilder::from_env`](crate::tokens::UserTokenBuilder::from_env)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+pub enum FromEnvError {
+    /// environment variable `{name}` is not set or not valid unicode
+    Var {
+        /// Name of the environment variable
+        name: &'static str,
+        /// Error from reading the variable
+        #[source]
+        source: std::env::VarError,
+    },
+}
+
+/// Errors for decoding an ID token with [`IdTokenClaims::decode_unverified`](crate::id::IdTokenClaims::decode_unverified)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+pub enum IdTokenError {
+    /// id token is not a JWT with three parts
+    Malformed,
+    /// could not decode the base64 of the id token
+    Base64(#[from] base64::DecodeError),
+    /// could not parse the claims of the id token
+    Json(#[from] serde_json::Error),
+}
+
+/// Errors for [`TokenStore::load_fresh`](crate::tokens::TokenStore::load_fresh)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+#[cfg(feature = "client")]
+pub enum TokenStoreError<
+    SE: std::error::Error + Send + Sync + 'static,
+    RE: std::error::Error + Send + Sync + 'static,
+> {
+    /// token store failed
+    Store(#[source] SE),
+    /// could not refresh token
+    Refresh(#[from] RefreshTokenError<RE>),
+}
+
+/// Errors for [`verify_id_token`](crate::oidc::verify_id_token)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+#[cfg(feature = "oidc")]
+pub enum IdTokenVerificationError<RE: std::error::Error + Send + Sync + 'static> {
+    /// request for the signing keys failed
+    Request(#[source] RE),
+    /// could not parse response when getting the signing keys
+    RequestParseError(#[from] crate::RequestParseError),
+    /// discovery document has an invalid `jwks_uri`
+    InvalidJwksUri(#[from] url::ParseError),
+    /// id token is not signed with a known key
+    UnknownKey,
+    /// id token is invalid
+    Invalid(#[from] jsonwebtoken::errors::Error),
+    /// nonce of the id token does not match
+    NonceMismatch,
 }
 
 /// Errors for [AccessToken::validate_token][crate::AccessTokenRef::validate_token] and [UserToken::from_response][crate::tokens::UserToken::from_response]
+///
+/// - [`Request`](ValidationError::Request) if the http client failed, like on a network error
+/// - [`NotAuthorized`](ValidationError::NotAuthorized) or [`Expired`](ValidationError::Expired) if twitch rejected the token
+/// - [`NoLogin`](ValidationError::NoLogin) if a user token was expected, but twitch returned no user
+/// - [`RequestParseError`](ValidationError::RequestParseError) for other errors returned by twitch, in [`RequestParseError::TwitchError`](crate::RequestParseError::TwitchError),
+///   or responses that could not be parsed, in [`RequestParseError::InvalidBody`](crate::RequestParseError::InvalidBody) with the body of the response
 #[derive(thiserror::Error, Debug, displaydoc::Display)]
 #[non_exhaustive]
 pub enum ValidationError<RE: std::error::Error + Send + Sync + 'static> {
     /// token is not authorized for use
     NotAuthorized,
+    /// token has expired
+    ///
+    /// This is best-effort, twitch usually rejects expired and invalid tokens alike with `invalid access token`.
+    /// Returned instead of [`ValidationError::NotAuthorized`] when validating a [`TwitchToken`](crate::TwitchToken) whose lifetime has passed,
+    /// or when the message from twitch mentions that the token expired.
+    Expired,
     /// could not parse response when validating token
     RequestParseError(#[from] crate::RequestParseError),
     /// failed to request validation
     Request(#[source] RE),
     /// given token is not of the correct token type: {0}
     InvalidToken(&'static str),
+    /// validation did not include a `login` or `user_id`, token might be an app access token
+    NoLogin,
+    /// token belongs to client id `{found}`, expected `{expected}`
+    ClientIdMismatch {
+        /// The expected client id
+        expected: crate::ClientId,
+        /// The client id of the token
+        found: crate::ClientId,
+    },
+}
+
+impl<RE: std::error::Error + Send + Sync + 'static> ValidationError<RE> {
+    /// Create the error for an unauthorized validation, telling an expired token apart from an invalid one with the message from twitch if it says so.
+    pub(crate) fn unauthorized<B: AsRef<[u8]>>(response: &http::Response<B>) -> Self {
+        match serde_json::from_slice::<crate::id::TwitchTokenErrorResponse>(
+            response.body().as_ref(),
+        ) {
+            Ok(e) if e.message.to_ascii_lowercase().contains("expired") => ValidationError::Expired,
+            _ => ValidationError::NotAuthorized,
+        }
+    }
 }
 
 impl ValidationError<std::convert::Infallible> {
@@ -31,8 +160,13 @@ impl ValidationError<std::convert::Infallible> {
     pub fn into_other<RE: std::error::Error + Send + Sync + 'static>(self) -> ValidationError<RE> {
         match self {
             ValidationError::NotAuthorized => ValidationError::NotAuthorized,
+            ValidationError::Expired => ValidationError::Expired,
             ValidationError::RequestParseError(e) => ValidationError::RequestParseError(e),
             ValidationError::InvalidToken(s) => ValidationError::InvalidToken(s),
+            ValidationError::NoLogin => ValidationError::NoLogin,
+            ValidationError::ClientIdMismatch { expected, found } => {
+                ValidationError::ClientIdMismatch { expected, found }
+            }
             ValidationError::Request(_) => unreachable!(),
         }
     }
@@ -68,9 +202,29 @@ pub enum RefreshTokenError<RE: std::error::Error + Send + Sync + 'static> {
     NoRefreshToken,
     /// no expiration found on new token
     NoExpiration,
+    /// could not validate the refreshed token to get its scopes
+    ValidationError(#[from] ValidationError<RE>),
+    /// token can not be refreshed: {reason}
+    NotRefreshable {
+        /// Why the token can not be refreshed
+        reason: &'static str,
+    },
+}
+
+/// Errors for [`UserToken::validate_or_refresh`](crate::tokens::UserToken::validate_or_refresh)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+#[cfg(feature = "client")]
+pub enum ValidateOrRefreshError<RE: std::error::Error + Send + Sync + 'static> {
+    /// could not validate token
+    Validation(#[source] ValidationError<RE>),
+    /// token is not valid and could not be refreshed
+    Refresh(#[source] RefreshTokenError<RE>),
+    /// token was refreshed but is still not valid
+    StillInvalid(#[source] ValidationError<RE>),
 }
 
-/// Errors for [`UserTokenBuilder::get_user_token`](crate::tokens::UserTokenBuilder::get_user_token) and [`UserToken::mock_token`](crate::tokens::UserToken::mock_token)
+/// Errors for [`UserTokenBuilder::get_user_token`](crate::tokens::UserTokenBuilder::get_user_token), [`UserTokenBuilder::complete_exchange`](crate::tokens::UserTokenBuilder::complete_exchange) and [`UserToken::mock_token`](crate::tokens::UserToken::mock_token)
 #[derive(thiserror::Error, Debug, displaydoc::Display)]
 #[non_exhaustive]
 #[cfg(feature = "client")]
@@ -81,6 +235,63 @@ pub enum UserTokenExchangeError<RE: std::error::Error + Send + Sync + 'static> {
     RequestParseError(#[from] crate::RequestParseError),
     /// state CSRF does not match when exchanging user token
     StateMismatch,
+    /// no authorization code found, the exchange has not been prepared
+    NoCode,
+    /// could not get validation for user token
+    ValidationError(#[from] ValidationError<RE>),
+}
+
+/// Errors for [`UserTokenBuilder::authenticate_local`](crate::tokens::UserTokenBuilder::authenticate_local)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+#[cfg(feature = "local-server")]
+pub enum LocalServerError<RE: std::error::Error + Send + Sync + 'static> {
+    /// could not receive the redirect on the local server
+    Io(#[from] std::io::Error),
+    /// timed out waiting for the redirect on the local server
+    Timeout,
+    /// could not open the browser, authenticate at {url}
+    OpenBrowser {
+        /// The url the user should visit to authenticate
+        url: url::Url,
+        /// The error when opening the url
+        #[source]
+        error: std::io::Error,
+    },
+    /// twitch returned an error: {error:?} - {description:?}
+    TwitchError {
+        /// Error type
+        error: Option<String>,
+        /// Description of error
+        description: Option<String>,
+    },
+    /// could not exchange the authorization code
+    Exchange(#[from] UserTokenExchangeError<RE>),
+}
+
+/// Errors for [`DeviceUserTokenBuilder::start`](crate::tokens::DeviceUserTokenBuilder::start) and [`DeviceUserTokenBuilder::poll`](crate::tokens::DeviceUserTokenBuilder::poll)
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+#[cfg(feature = "client")]
+pub enum DeviceUserTokenExchangeError<RE: std::error::Error + Send + Sync + 'static> {
+    /// request for device code or user token failed
+    RequestError(#[source] RE),
+    /// could not parse response when getting device code or user token
+    RequestParseError(#[from] crate::RequestParseError),
+    /// no device code found, the device flow has not been started
+    NoDeviceCode,
+    /// the device code expired before the user authorized the device
+    ExpiredToken,
+    /// the user denied the authorization request
+    AccessDenied,
+    /// twitch rejected a requested scope, requested scopes: {requested_scopes:?}
+    InvalidScope {
+        /// The scopes that were requested
+        requested_scopes: Vec<crate::Scope>,
+        /// The error from twitch
+        #[source]
+        error: crate::id::TwitchTokenErrorResponse,
+    },
     /// could not get validation for user token
     ValidationError(#[from] ValidationError<RE>),
 }
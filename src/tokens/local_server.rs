@@ -0,0 +1,259 @@
+//! Minimal localhost listener used by [`UserTokenBuilder::authenticate_local`](super::UserTokenBuilder::authenticate_local)
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+const SUCCESS_PAGE: &str =
+    "<html><body>Authentication finished, you can close this window.</body></html>";
+
+/// The query parameters twitch redirected the user with
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Callback {
+    pub state: Option<String>,
+    pub code: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+impl Callback {
+    fn parse(url: &url::Url) -> Self {
+        let mut callback = Self::default();
+        for (key, value) in url.query_pairs() {
+            let value = value.into_owned();
+            match key.as_ref() {
+                "state" => callback.state = Some(value),
+                "code" => callback.code = Some(value),
+                "error" => callback.error = Some(value),
+                "error_description" => callback.error_description = Some(value),
+                _ => {}
+            }
+        }
+        callback
+    }
+}
+
+/// Open `url` in the default browser of the system
+pub(crate) fn open_browser(url: &url::Url) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    let status = command.arg(url.as_str()).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("could not open browser: {status}"),
+        ))
+    }
+}
+
+/// How often the listener checks for a connection, the deadline and cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Accept connections on `listener` until a request to `path` carries a `code` or an `error`.
+///
+/// Other requests, like the browser asking for a favicon, get a 404. The listener runs on its own thread, so the returned future doesn't block the executor.
+/// The thread stops and closes the listener when `timeout` passes, returning an error of kind [`TimedOut`](io::ErrorKind::TimedOut), or when the future is dropped.
+pub(crate) fn wait_for_redirect(
+    listener: TcpListener,
+    path: String,
+    timeout: Duration,
+) -> io::Result<impl Future<Output = io::Result<Callback>>> {
+    listener.set_nonblocking(true)?;
+    let deadline = Instant::now() + timeout;
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let thread_shared = shared.clone();
+    let thread_cancelled = cancelled.clone();
+    std::thread::spawn(move || {
+        let result = accept_redirect(&listener, &path, deadline, &thread_cancelled);
+        let mut shared = thread_shared.lock().expect("poisoned lock");
+        shared.result = Some(result);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    });
+    Ok(Redirect { shared, cancelled })
+}
+
+fn accept_redirect(
+    listener: &TcpListener,
+    path: &str,
+    deadline: Instant,
+    cancelled: &AtomicBool,
+) -> io::Result<Callback> {
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "stopped waiting for the redirect",
+            ));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "timed out waiting for the redirect",
+            ));
+        }
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        // A broken connection shouldn't abort the flow, the browser can retry.
+        if let Ok(Some(callback)) = handle_connection(stream, path) {
+            return Ok(callback);
+        }
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, path: &str) -> io::Result<Option<Callback>> {
+    // The accepted stream can inherit nonblocking mode from the listener, and a stalled client shouldn't hold up the deadline.
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Read the rest of the headers, closing with unread data would reset the connection.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    // The request line is `GET <target> HTTP/1.1`
+    let callback = request_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|target| url::Url::parse(&format!("http://localhost{target}")).ok())
+        .filter(|url| url.path() == path)
+        .map(|url| Callback::parse(&url))
+        .filter(|callback| callback.code.is_some() || callback.error.is_some());
+
+    let response = match callback {
+        Some(_) => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{SUCCESS_PAGE}",
+            SUCCESS_PAGE.len()
+        ),
+        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned(),
+    };
+    stream.write_all(response.as_bytes())?;
+    stream.flush()?;
+    Ok(callback)
+}
+
+#[derive(Default)]
+struct Shared {
+    result: Option<io::Result<Callback>>,
+    waker: Option<Waker>,
+}
+
+struct Redirect {
+    shared: Arc<Mutex<Shared>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for Redirect {
+    fn drop(&mut self) { self.cancelled.store(true, Ordering::Relaxed); }
+}
+
+impl Future for Redirect {
+    type Output = io::Result<Callback>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().expect("poisoned lock");
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(addr: std::net::SocketAddr, target: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {target} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        io::Read::read_to_string(&mut stream, &mut response).unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn wait_for_redirect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let redirect =
+            super::wait_for_redirect(listener, "/callback".to_owned(), Duration::from_secs(10))
+                .unwrap();
+
+        let requests = std::thread::spawn(move || {
+            let not_found = request(addr, "/favicon.ico");
+            let missing_code = request(addr, "/callback?state=abc");
+            let found = request(addr, "/callback?code=secret&state=abc&scope=");
+            (not_found, missing_code, found)
+        });
+        let callback = redirect.await.unwrap();
+        let (not_found, missing_code, found) = requests.join().unwrap();
+
+        assert!(not_found.starts_with("HTTP/1.1 404"));
+        assert!(missing_code.starts_with("HTTP/1.1 404"));
+        assert!(found.starts_with("HTTP/1.1 200"));
+        assert_eq!(
+            callback,
+            Callback {
+                state: Some("abc".to_owned()),
+                code: Some("secret".to_owned()),
+                ..Callback::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_for_redirect_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let redirect =
+            super::wait_for_redirect(listener, "/callback".to_owned(), Duration::from_millis(10))
+                .unwrap();
+        let error = redirect.await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn wait_for_redirect_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let redirect =
+            super::wait_for_redirect(listener, "/callback".to_owned(), Duration::from_secs(60))
+                .unwrap();
+        drop(redirect);
+
+        // The listener thread notices the future is gone and frees the port.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while TcpListener::bind(addr).is_err() {
+            assert!(Instant::now() < deadline, "port was not freed");
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use twitch_types::{UserId, UserIdRef};
+
+use super::errors::TokenStoreError;
+use crate::client::Client;
+use crate::tokens::{TwitchToken, UserToken};
+use crate::types::ClientSecret;
+
+/// Storage for [user tokens](UserToken), keyed by their [user id](UserToken::user_id).
+///
+/// Use [`load_fresh`](TokenStore::load_fresh) to get a valid token for a user, refreshing and storing it when needed.
+///
+/// # Notes
+///
+/// The client secret is not persisted by [`FileTokenStore`], see [`UserToken`] serialization.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use twitch_oauth2::tokens::{MemoryTokenStore, TokenStore};
+/// use twitch_oauth2::twitch_types::UserIdRef;
+/// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::builder()
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()?;
+/// # );
+/// let store = MemoryTokenStore::new();
+/// let token = store
+///     .load_fresh(
+///         &client,
+///         UserIdRef::from_static("1234"),
+///         Some("my_client_secret".into()),
+///         std::time::Duration::from_secs(60),
+///     )
+///     .await?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())};
+/// ```
+#[async_trait::async_trait]
+pub trait TokenStore: Send + Sync {
+    /// Error returned by the store
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Load the token of a user, `None` if there is no token stored for the user.
+    async fn load(&self, user_id: &UserIdRef) -> Result<Option<UserToken>, Self::Error>;
+
+    /// Store a token, replacing the token stored for the same user.
+    async fn store(&self, token: &UserToken) -> Result<(), Self::Error>;
+
+    /// Load the token of a user, refreshing and storing it if it expires within `threshold`.
+    ///
+    /// `client_secret` is set on the loaded token if given, as it's needed to refresh.
+    async fn load_fresh<C>(
+        &self,
+        http_client: &C,
+        user_id: &UserIdRef,
+        client_secret: Option<ClientSecret>,
+        threshold: Duration,
+    ) -> Result<Option<UserToken>, TokenStoreError<Self::Error, <C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let Some(mut token) = self.load(user_id).await.map_err(TokenStoreError::Store)? else {
+            return Ok(None);
+        };
+        if client_secret.is_some() {
+            token.set_secret(client_secret);
+        }
+        if token.expires_within(threshold) || token.is_elapsed() {
+            token.refresh_token(http_client).await?;
+            self.store(&token).await.map_err(TokenStoreError::Store)?;
+        }
+        Ok(Some(token))
+    }
+}
+
+/// A [`TokenStore`] keeping tokens in memory.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    tokens: Mutex<HashMap<UserId, UserToken>>,
+}
+
+impl MemoryTokenStore {
+    /// Create an empty [`MemoryTokenStore`]
+    pub fn new() -> MemoryTokenStore { MemoryTokenStore::default() }
+}
+
+#[async_trait::async_trait]
+impl TokenStore for MemoryTokenStore {
+    type Error = std::convert::Infallible;
+
+    async fn load(&self, user_id: &UserIdRef) -> Result<Option<UserToken>, Self::Error> {
+        Ok(self
+            .tokens
+            .lock()
+            .expect("token store should not be poisoned")
+            .get(user_id)
+            .cloned())
+    }
+
+    async fn store(&self, token: &UserToken) -> Result<(), Self::Error> {
+        self.tokens
+            .lock()
+            .expect("token store should not be poisoned")
+            .insert(token.user_id.clone(), token.clone());
+        Ok(())
+    }
+}
+
+/// A [`TokenStore`] keeping each token as a JSON file named `<user_id>.json` in a directory.
+///
+/// The files are read and written with blocking io. Files are replaced atomically, and on unix they are only readable by the current user.
+#[derive(Debug, Clone)]
+pub struct FileTokenStore {
+    dir: PathBuf,
+}
+
+/// Errors for [`FileTokenStore`]
+#[derive(thiserror::Error, Debug, displaydoc::Display)]
+#[non_exhaustive]
+pub enum FileTokenStoreError {
+    /// could not read or write the token file
+    Io(#[from] std::io::Error),
+    /// could not (de)serialize the token
+    Json(#[from] serde_json::Error),
+    /// user id `{0}` can not be used as a file name
+    InvalidUserId(UserId),
+}
+
+impl FileTokenStore {
+    /// Create a [`FileTokenStore`] storing tokens in `dir`, the directory is created when storing the first token.
+    pub fn new(dir: impl Into<PathBuf>) -> FileTokenStore { FileTokenStore { dir: dir.into() } }
+
+    fn path(&self, user_id: &UserIdRef) -> Result<PathBuf, FileTokenStoreError> {
+        // User ids are numeric, anything else could escape the directory.
+        if user_id.as_str().is_empty()
+            || !user_id.as_str().chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(FileTokenStoreError::InvalidUserId(user_id.to_owned()));
+        }
+        Ok(self.dir.join(format!("{user_id}.json")))
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenStore for FileTokenStore {
+    type Error = FileTokenStoreError;
+
+    async fn load(&self, user_id: &UserIdRef) -> Result<Option<UserToken>, Self::Error> {
+        match std::fs::read(self.path(user_id)?) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn store(&self, token: &UserToken) -> Result<(), Self::Error> {
+        let path = self.path(&token.user_id)?;
+        std::fs::create_dir_all(&self.dir)?;
+        let contents = serde_json::to_vec(token)?;
+        // Write to a temporary file and rename it, so a crash while writing can't leave a truncated token file.
+        let tmp = path.with_extension(format!(
+            "json.{}-{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        let result = write_private(&tmp, &contents).and_then(|()| std::fs::rename(&tmp, &path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        Ok(result?)
+    }
+}
+
+/// Counter for unique temporary file names when storing concurrently
+static TMP_COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Write `contents` to a new file at `path`, only readable and writable by the current user on unix.
+fn write_private(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write as _;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+
+    fn token() -> UserToken {
+        UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .refresh_token("refresh")
+            .expires_in(Duration::from_secs(10))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn memory_store() {
+        let store = MemoryTokenStore::new();
+        assert!(store
+            .load(UserIdRef::from_static("1234"))
+            .await
+            .unwrap()
+            .is_none());
+        store.store(&token()).await.unwrap();
+        let loaded = store
+            .load(UserIdRef::from_static("1234"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.access_token.secret(), "token");
+    }
+
+    #[tokio::test]
+    async fn file_store() {
+        let dir = std::env::temp_dir().join(format!("twitch_oauth2_store_{}", std::process::id()));
+        let store = FileTokenStore::new(&dir);
+        assert!(store
+            .load(UserIdRef::from_static("1234"))
+            .await
+            .unwrap()
+            .is_none());
+        store.store(&token()).await.unwrap();
+        let loaded = store
+            .load(UserIdRef::from_static("1234"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.access_token.secret(), "token");
+        assert_eq!(loaded.login.as_str(), "user");
+        // Only the token file is left, without temporary files.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            let mode = std::fs::metadata(dir.join("1234.json"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // Storing again replaces the file.
+        store.store(&token()).await.unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(matches!(
+            store.load(UserIdRef::from_static("../1234")).await,
+            Err(FileTokenStoreError::InvalidUserId(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn load_fresh() {
+        let store = MemoryTokenStore::new();
+        store.store(&token()).await.unwrap();
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("new_token", Some("new_refresh"), &[], 3600),
+        );
+
+        // The token expires in 10 seconds, so it's not refreshed with a smaller threshold
+        let token = store
+            .load_fresh(
+                &client,
+                UserIdRef::from_static("1234"),
+                None,
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token.access_token.secret(), "token");
+
+        let token = store
+            .load_fresh(
+                &client,
+                UserIdRef::from_static("1234"),
+                Some("secret".into()),
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token.access_token.secret(), "new_token");
+        let stored = store
+            .load(UserIdRef::from_static("1234"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.access_token.secret(), "new_token");
+        assert!(store
+            .load_fresh(
+                &client,
+                UserIdRef::from_static("4321"),
+                None,
+                Duration::from_secs(60)
+            )
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -2,7 +2,10 @@ use twitch_types::{UserId, UserIdRef, UserName, UserNameRef};
 
 use super::errors::ValidationError;
 #[cfg(feature = "client")]
-use super::errors::{ImplicitUserTokenExchangeError, RefreshTokenError, UserTokenExchangeError};
+use super::errors::{
+    DeviceUserTokenExchangeError, ImplicitUserTokenExchangeError, RefreshTokenError,
+    UserTokenExchangeError, ValidateOrRefreshError,
+};
 #[cfg(feature = "client")]
 use crate::client::Client;
 
@@ -10,13 +13,24 @@ use crate::tokens::{Scope, TwitchToken};
 use crate::{ClientSecret, ValidatedToken};
 
 use crate::types::{AccessToken, ClientId, RefreshToken};
+use serde_derive::{Deserialize, Serialize};
 
 /// An User Token from the [OAuth implicit code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#implicit-grant-flow) or [OAuth authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow)
 ///
 /// Used for requests that need an authenticated user. See also [`AppAccessToken`](super::AppAccessToken)
 ///
 /// See [`UserToken::builder`](UserTokenBuilder::new) for authenticating the user using the `OAuth authorization code flow`.
-#[derive(Clone)]
+///
+/// # Serialization
+///
+/// The token can be (de)serialized with serde to persist it. The expiry is stored as a unix timestamp,
+/// so [`expires_in`](TwitchToken::expires_in) stays correct after deserializing.
+///
+/// The client secret is never serialized, use [`UserToken::set_secret`] to set it again after deserializing.
+///
+/// Deserialized tokens use the system clock for their expiry, see [`UncheckedUserTokenBuilder::with_system_clock`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "SerializedUserToken", into = "SerializedUserToken")]
 pub struct UserToken {
     /// The access token used to authenticate requests with
     pub access_token: AccessToken,
@@ -32,13 +46,74 @@ pub struct UserToken {
     expires_in: std::time::Duration,
     /// When this struct was created, not when token was created.
     struct_created: std::time::Instant,
+    /// When the token expires according to the system clock, used instead of `struct_created` and `expires_in` if set.
+    system_expiry: Option<std::time::SystemTime>,
     scopes: Vec<Scope>,
     /// Token will never expire
     ///
-    /// This is only true for old client IDs, like <https://twitchapps.com/tmi> and others
+    /// This is only true for old client IDs, like <https://twitchapps.com/tmi> and others.
+    /// Twitch reports these tokens with an `expires_in` of `0` when validating.
     pub never_expiring: bool,
 }
 
+/// Representation of [`UserToken`] used for (de)serialization
+#[derive(Serialize, Deserialize)]
+struct SerializedUserToken {
+    access_token: AccessToken,
+    client_id: ClientId,
+    login: UserName,
+    user_id: UserId,
+    refresh_token: Option<RefreshToken>,
+    /// Unix timestamp in seconds of when the token expires, `None` if the token never expires
+    expires_at: Option<u64>,
+    scopes: Vec<Scope>,
+}
+
+impl From<UserToken> for SerializedUserToken {
+    fn from(token: UserToken) -> Self {
+        let expires_at = token.expires_at().map(|expires_at| {
+            expires_at
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        });
+        SerializedUserToken {
+            access_token: token.access_token,
+            client_id: token.client_id,
+            login: token.login,
+            user_id: token.user_id,
+            refresh_token: token.refresh_token,
+            expires_at,
+            scopes: token.scopes,
+        }
+    }
+}
+
+impl From<SerializedUserToken> for UserToken {
+    fn from(token: SerializedUserToken) -> Self {
+        let expires_at = token
+            .expires_at
+            .map(|expires_at| std::time::UNIX_EPOCH + std::time::Duration::from_secs(expires_at));
+        let expires_in = expires_at.map(|expires_at| {
+            expires_at
+                .duration_since(std::time::SystemTime::now())
+                .unwrap_or_default()
+        });
+        let mut user_token = UserToken::from_existing_unchecked(
+            token.access_token,
+            token.refresh_token,
+            token.client_id,
+            None,
+            token.login,
+            token.user_id,
+            Some(token.scopes),
+            expires_in,
+        );
+        user_token.system_expiry = expires_at;
+        user_token
+    }
+}
+
 impl std::fmt::Debug for UserToken {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("UserToken")
@@ -54,10 +129,31 @@ impl std::fmt::Debug for UserToken {
     }
 }
 
+/// Tokens are equal if they are for the same user and client id.
+///
+/// The access token is intentionally not compared, so a refreshed token is still equal to the original.
+impl PartialEq for UserToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.user_id == other.user_id && self.client_id == other.client_id
+    }
+}
+
+impl Eq for UserToken {}
+
+/// Hashed by user and client id, consistent with [`PartialEq`].
+impl std::hash::Hash for UserToken {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.user_id.hash(state);
+        self.client_id.hash(state);
+    }
+}
+
 impl UserToken {
     /// Create a new token
     ///
     /// See [`UserToken::from_token`] and [`UserToken::from_existing`] for more ways to create a [`UserToken`]
+    ///
+    /// Fails with [`ValidationError::NoLogin`] if `validated` has no `login` or `user_id`, as for an app access token.
     pub fn new(
         access_token: AccessToken,
         refresh_token: Option<RefreshToken>,
@@ -69,12 +165,8 @@ impl UserToken {
             refresh_token,
             validated.client_id,
             client_secret,
-            validated.login.ok_or(ValidationError::InvalidToken(
-                "validation did not include a `login`, token might be an app access token",
-            ))?,
-            validated.user_id.ok_or(ValidationError::InvalidToken(
-                "validation did not include a `user_id`, token might be an app access token",
-            ))?,
+            validated.login.ok_or(ValidationError::NoLogin)?,
+            validated.user_id.ok_or(ValidationError::NoLogin)?,
             validated.scopes,
             validated.expires_in,
         ))
@@ -82,7 +174,7 @@ impl UserToken {
 
     /// Create a [UserToken] from an existing active user token. Retrieves [`login`](TwitchToken::login), [`client_id`](TwitchToken::client_id) and [`scopes`](TwitchToken::scopes)
     ///
-    /// If the token is already expired, this function will fail to produce a [`UserToken`] and return [`ValidationError::NotAuthorized`]
+    /// If the token is already expired, this function will fail to produce a [`UserToken`] and return [`ValidationError::NotAuthorized`] or [`ValidationError::Expired`]
     ///
     /// # Examples
     ///
@@ -110,7 +202,7 @@ impl UserToken {
 
     /// Create a [UserToken] from an existing active user token. Retrieves [`login`](TwitchToken::login), [`client_id`](TwitchToken::client_id) and [`scopes`](TwitchToken::scopes)
     ///
-    /// If the token is already expired, this function will fail to produce a [`UserToken`] and return [`ValidationError::NotAuthorized`]
+    /// If the token is already expired, this function will fail to produce a [`UserToken`] and return [`ValidationError::NotAuthorized`] or [`ValidationError::Expired`]
     ///
     /// # Examples
     ///
@@ -141,7 +233,77 @@ impl UserToken {
     where
         C: Client,
     {
-        let validated = access_token.validate_token(http_client).await?;
+        Self::from_existing_with_retries(http_client, access_token, refresh_token, client_secret, 0)
+            .await
+    }
+
+    /// Create a [UserToken] from an existing active user token, like [`from_existing`](UserToken::from_existing), validating it with [`TwitchUrls::validate_url`](crate::TwitchUrls::validate_url).
+    #[cfg(feature = "client")]
+    pub async fn from_existing_with_urls<C>(
+        http_client: &C,
+        access_token: AccessToken,
+        refresh_token: impl Into<Option<RefreshToken>>,
+        client_secret: impl Into<Option<ClientSecret>>,
+        urls: &crate::TwitchUrls,
+    ) -> Result<UserToken, ValidationError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let validated = access_token
+            .validate_token_with_urls(http_client, urls)
+            .await?;
+        Self::new(access_token, refresh_token.into(), validated, client_secret)
+            .map_err(|e| e.into_other())
+    }
+
+    /// Create a [UserToken] from an existing active user token, like [`from_existing`](UserToken::from_existing), and check that it belongs to `expected_client_id`.
+    ///
+    /// Fails with [`ValidationError::ClientIdMismatch`] if the token was issued to another application, for example when loading a persisted token.
+    #[cfg(feature = "client")]
+    pub async fn from_existing_for_client<C>(
+        http_client: &C,
+        access_token: AccessToken,
+        refresh_token: impl Into<Option<RefreshToken>>,
+        client_secret: impl Into<Option<ClientSecret>>,
+        expected_client_id: &crate::ClientIdRef,
+    ) -> Result<UserToken, ValidationError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let token =
+            Self::from_existing(http_client, access_token, refresh_token, client_secret).await?;
+        if &*token.client_id != expected_client_id {
+            return Err(ValidationError::ClientIdMismatch {
+                expected: expected_client_id.to_owned(),
+                found: token.client_id.clone(),
+            });
+        }
+        Ok(token)
+    }
+
+    /// Create a [UserToken] from an existing active user token, retrying the validation up to `retries` times if the request fails.
+    ///
+    /// Only failed requests, like network errors, are retried. Responses from twitch, like an expired token, are not.
+    ///
+    /// See [`from_existing`](UserToken::from_existing)
+    #[cfg(feature = "client")]
+    pub async fn from_existing_with_retries<C>(
+        http_client: &C,
+        access_token: AccessToken,
+        refresh_token: impl Into<Option<RefreshToken>>,
+        client_secret: impl Into<Option<ClientSecret>>,
+        retries: u32,
+    ) -> Result<UserToken, ValidationError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let mut attempt = 0;
+        let validated = loop {
+            match access_token.validate_token(http_client).await {
+                Err(ValidationError::Request(_)) if attempt < retries => attempt += 1,
+                validated => break validated?,
+            }
+        };
         Self::new(access_token, refresh_token.into(), validated, client_secret)
             .map_err(|e| e.into_other())
     }
@@ -171,17 +333,23 @@ impl UserToken {
             refresh_token: refresh_token.into(),
             expires_in: expires_in.unwrap_or(std::time::Duration::MAX),
             struct_created: std::time::Instant::now(),
+            system_expiry: None,
             scopes: scopes.unwrap_or_default(),
             never_expiring: expires_in.is_none(),
         }
     }
 
     /// Assemble token from twitch responses.
+    ///
+    /// The scopes are taken from the validation, or from the token response if the validation has none.
     pub fn from_response(
         response: crate::id::TwitchTokenResponse,
-        validated: ValidatedToken,
+        mut validated: ValidatedToken,
         client_secret: impl Into<Option<ClientSecret>>,
     ) -> Result<UserToken, ValidationError<std::convert::Infallible>> {
+        if validated.scopes.is_none() {
+            validated.scopes = response.scopes;
+        }
         Self::new(
             response.access_token,
             response.refresh_token,
@@ -196,6 +364,95 @@ impl UserToken {
     /// Hidden because it's not expected to be used.
     pub fn never_expires(&self) -> bool { self.never_expiring }
 
+    /// Get when this token was created or last refreshed.
+    ///
+    /// This is when this struct was created, not when twitch created the token.
+    pub fn created_at(&self) -> std::time::Instant { self.struct_created }
+
+    /// Get the lifetime the token was granted with when it was [created](UserToken::created_at), or `None` if the token never expires.
+    ///
+    /// Unlike [`expires_in`](TwitchToken::expires_in), this does not decrease over time.
+    pub fn initial_expires_in(&self) -> Option<std::time::Duration> {
+        (!self.never_expiring).then_some(self.expires_in)
+    }
+
+    /// Set the lifetime of the token from now, `None` means the token never expires.
+    #[cfg(feature = "client")]
+    fn set_expiry(&mut self, expires: Option<std::time::Duration>) {
+        self.struct_created = std::time::Instant::now();
+        self.expires_in = expires.unwrap_or(std::time::Duration::MAX);
+        self.never_expiring = expires.is_none();
+        if let (Some(_), Some(expires)) = (self.system_expiry, expires) {
+            self.system_expiry = std::time::SystemTime::now().checked_add(expires);
+        }
+    }
+
+    /// Get a random point in time within `window` before the token expires, at which the token should be refreshed.
+    ///
+    /// Spreads out refreshing when many tokens expire at the same time. Returns `None` if the token never expires.
+    ///
+    /// See [`refresh_instant_with_jitter_rng`](UserToken::refresh_instant_with_jitter_rng) to use a specific random number generator.
+    pub fn refresh_instant_with_jitter(
+        &self,
+        window: std::time::Duration,
+    ) -> Option<std::time::Instant> {
+        self.refresh_instant_with_jitter_rng(window, &mut rand::thread_rng())
+    }
+
+    /// Get a random point in time within `window` before the token expires, using the given random number generator.
+    ///
+    /// See [`refresh_instant_with_jitter`](UserToken::refresh_instant_with_jitter)
+    pub fn refresh_instant_with_jitter_rng<R: rand::Rng + ?Sized>(
+        &self,
+        window: std::time::Duration,
+        rng: &mut R,
+    ) -> Option<std::time::Instant> {
+        if self.never_expiring {
+            return None;
+        }
+        let expires = match self.system_expiry {
+            Some(_) => std::time::Instant::now().checked_add(self.expires_in())?,
+            None => self.struct_created.checked_add(self.expires_in)?,
+        };
+        let jitter = rng.gen_range(std::time::Duration::ZERO..=window);
+        Some(expires.checked_sub(jitter).unwrap_or(self.struct_created))
+    }
+
+    /// Create an [`UncheckedUserTokenBuilder`] to assemble a token without checks.
+    ///
+    /// Less error prone than [`from_existing_unchecked`](UserToken::from_existing_unchecked) when restoring a token from storage.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{Scope, TwitchToken, UserToken};
+    ///
+    /// let token = UserToken::unchecked_builder("token", "client_id", "justintv", "1337")
+    ///     .refresh_token("refresh_token")
+    ///     .scopes(vec![Scope::ChatRead])
+    ///     .expires_in(std::time::Duration::from_secs(3600))
+    ///     .build();
+    /// assert!(token.has_scope(&Scope::ChatRead));
+    /// ```
+    pub fn unchecked_builder(
+        access_token: impl Into<AccessToken>,
+        client_id: impl Into<ClientId>,
+        login: impl Into<UserName>,
+        user_id: impl Into<UserId>,
+    ) -> UncheckedUserTokenBuilder {
+        UncheckedUserTokenBuilder {
+            access_token: access_token.into(),
+            client_id: client_id.into(),
+            login: login.into(),
+            user_id: user_id.into(),
+            client_secret: None,
+            refresh_token: None,
+            scopes: vec![],
+            expires_in: None,
+            system_clock: false,
+        }
+    }
+
     /// Create a [`UserTokenBuilder`] to get a token with the [OAuth Authorization Code](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow)
     pub fn builder(
         client_id: ClientId,
@@ -274,7 +531,183 @@ impl UserToken {
     }
 
     /// Set the client secret
+    ///
+    /// A client secret alone is not enough to refresh the token, a refresh token is also needed.
+    /// Tokens from the [implicit flow](ImplicitUserTokenBuilder) never have a refresh token.
     pub fn set_secret(&mut self, secret: Option<ClientSecret>) { self.client_secret = secret }
+
+    /// Returns whether or not this token can be refreshed with [`refresh_token`](TwitchToken::refresh_token).
+    ///
+    /// A token can be refreshed if both a client secret and a refresh token are set.
+    pub fn can_refresh(&self) -> bool {
+        self.client_secret.is_some() && self.refresh_token.is_some()
+    }
+
+    /// Set the login of the user associated with this token, for example after the user changed their name.
+    pub fn set_login(&mut self, login: UserName) { self.login = login }
+
+    /// Validate the token and update the [`login`](UserToken::login) and [`scopes`](TwitchToken::scopes) from the response.
+    ///
+    /// Users can change their login on twitch, which makes the login stored in a long-lived token stale.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ValidationError::InvalidToken`] and leaves the token untouched if the validated `user_id` does not match [`user_id`](UserToken::user_id).
+    #[cfg(feature = "client")]
+    pub async fn refresh_identity<C>(
+        &mut self,
+        http_client: &C,
+    ) -> Result<(), ValidationError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let validated = self.access_token.validate_token(http_client).await?;
+        if validated.user_id.as_ref() != Some(&self.user_id) {
+            return Err(ValidationError::InvalidToken(
+                "validation returned a different `user_id` than the token has",
+            ));
+        }
+        if let Some(login) = validated.login {
+            self.login = login;
+        }
+        self.scopes = validated.scopes.unwrap_or_default();
+        Ok(())
+    }
+
+    /// Refresh the token like [`refresh_token`](TwitchToken::refresh_token), returning whether twitch rotated the refresh token.
+    ///
+    /// Twitch can return a new refresh token when refreshing, if the refresh token is persisted it should be replaced when [`RefreshOutcome::refresh_token_changed`] is `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .build()?;
+    /// # );
+    /// # let mut token: twitch_oauth2::UserToken = todo!();
+    /// let outcome = token.refresh_with_outcome(&client).await?;
+    /// if outcome.refresh_token_changed {
+    ///     // persist outcome.refresh_token
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())};
+    /// ```
+    #[cfg(feature = "client")]
+    pub async fn refresh_with_outcome<C>(
+        &mut self,
+        http_client: &C,
+    ) -> Result<RefreshOutcome, RefreshTokenError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let old = self.refresh_token.clone();
+        self.refresh_token(http_client).await?;
+        Ok(RefreshOutcome {
+            refresh_token_changed: old.as_ref().map(|t| t.secret())
+                != self.refresh_token.as_ref().map(|t| t.secret()),
+            refresh_token: self.refresh_token.clone(),
+        })
+    }
+
+    /// Check that `new`, a token from authorizing the user again with more scopes, has all scopes of this token.
+    ///
+    /// Useful for incremental authorization, where scopes are requested when they are needed.
+    /// Returns `new` if it has all the scopes of this token, otherwise [`ScopeDowngrade`](super::errors::ScopeDowngrade) with `new` and the lost scopes.
+    /// To keep the scopes when authorizing again, request both the current and new scopes.
+    pub fn upgraded_from(
+        &self,
+        new: UserToken,
+    ) -> Result<UserToken, super::errors::ScopeDowngrade> {
+        let lost = new.missing_scopes(&self.scopes);
+        if lost.is_empty() {
+            Ok(new)
+        } else {
+            Err(super::errors::ScopeDowngrade {
+                token: Box::new(new),
+                lost,
+            })
+        }
+    }
+
+    /// Validate the token, refreshing it if twitch says it's expired or invalid.
+    ///
+    /// Returns the token with its [`login`](UserToken::login) and [`scopes`](TwitchToken::scopes) updated from the validation, see [`refresh_identity`](UserToken::refresh_identity).
+    ///
+    /// # Errors
+    ///
+    /// - [`ValidateOrRefreshError::Validation`] if the first validation failed for another reason than the token being invalid
+    /// - [`ValidateOrRefreshError::Refresh`] if the token is invalid and could not be refreshed
+    /// - [`ValidateOrRefreshError::StillInvalid`] if the refreshed token could not be validated
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .build()?;
+    /// # );
+    /// # let token: twitch_oauth2::UserToken = todo!();
+    /// let token = token.validate_or_refresh(&client).await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())};
+    /// ```
+    #[cfg(feature = "client")]
+    pub async fn validate_or_refresh<C>(
+        mut self,
+        http_client: &C,
+    ) -> Result<UserToken, ValidateOrRefreshError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        match self.refresh_identity(http_client).await {
+            Ok(()) => return Ok(self),
+            Err(ValidationError::NotAuthorized | ValidationError::Expired) => {}
+            Err(e) => return Err(ValidateOrRefreshError::Validation(e)),
+        }
+        self.refresh_token(http_client)
+            .await
+            .map_err(ValidateOrRefreshError::Refresh)?;
+        self.refresh_identity(http_client)
+            .await
+            .map_err(ValidateOrRefreshError::StillInvalid)?;
+        Ok(self)
+    }
+}
+
+/// Result of [`UserToken::refresh_with_outcome`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RefreshOutcome {
+    /// Whether twitch returned a different refresh token than the token had
+    pub refresh_token_changed: bool,
+    /// The refresh token after refreshing
+    pub refresh_token: Option<RefreshToken>,
+}
+
+/// Overwrites the access token, refresh token and client secret with zeroes.
+///
+/// The secrets are also zeroized when they are dropped, so this is only needed to wipe them while the token is still alive.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for UserToken {
+    fn zeroize(&mut self) {
+        use zeroize::Zeroize as _;
+
+        self.access_token.zeroize();
+        if let Some(refresh_token) = &mut self.refresh_token {
+            refresh_token.zeroize();
+        }
+        if let Some(client_secret) = &mut self.client_secret {
+            client_secret.zeroize();
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for UserToken {}
+
+impl AsRef<AccessToken> for UserToken {
+    fn as_ref(&self) -> &AccessToken { &self.access_token }
 }
 
 #[cfg_attr(feature = "client", async_trait::async_trait)]
@@ -298,26 +731,63 @@ impl TwitchToken for UserToken {
         Self: Sized,
         C: Client,
     {
-        if let Some(client_secret) = self.client_secret.clone() {
-            let (access_token, expires, refresh_token) =
-                if let Some(token) = self.refresh_token.take() {
-                    token
-                        .refresh_token(http_client, &self.client_id, &client_secret)
-                        .await?
-                } else {
-                    return Err(RefreshTokenError::NoRefreshToken);
-                };
-            self.access_token = access_token;
-            self.expires_in = expires;
-            self.refresh_token = refresh_token;
-            Ok(())
-        } else {
+        if self.client_secret.is_none() && self.refresh_token.is_none() {
+            return Err(RefreshTokenError::NotRefreshable {
+                reason: "token has no refresh token and no client secret. Tokens from the implicit flow can not be refreshed, the user must re-authenticate",
+            });
+        }
+        let Some(client_secret) = &self.client_secret else {
             return Err(RefreshTokenError::NoClientSecretFound);
+        };
+        // Don't take the refresh token, a failed request should leave the token refreshable.
+        let Some(refresh_token) = &self.refresh_token else {
+            return Err(RefreshTokenError::NoRefreshToken);
+        };
+        // The request owns its body, so the secret is only borrowed while building it and not held across the await.
+        let req = refresh_token.refresh_token_request(&self.client_id, client_secret);
+        crate::trace_request(&req);
+        let resp = http_client
+            .req(req)
+            .await
+            .map_err(RefreshTokenError::RequestError)?;
+        crate::trace_response(&resp);
+        let response = crate::id::TwitchTokenResponse::from_response(&resp)?;
+        let expires = response.expires_in();
+
+        // Store the new tokens before validating, the previous refresh token may already be invalidated by twitch.
+        self.access_token = response.access_token;
+        self.refresh_token = response.refresh_token;
+        if expires.is_some() {
+            self.set_expiry(expires);
+        }
+        // The user could have revoked scopes, so make sure they are up to date.
+        let has_scopes = response.scopes.is_some();
+        if let Some(scopes) = response.scopes {
+            self.scopes = scopes;
+        }
+        if expires.is_some() && has_scopes {
+            return Ok(());
+        }
+
+        // Only validate if the response is missing the expiry or scopes.
+        // If validation fails, the token keeps its previous scopes or expiry.
+        let validated = self.access_token.validate_token(http_client).await?;
+        if expires.is_none() {
+            // A missing expiry in the validation means the token never expires.
+            self.set_expiry(validated.expires_in);
+        }
+        if !has_scopes {
+            self.scopes = validated.scopes.unwrap_or_default();
         }
+        Ok(())
     }
 
     fn expires_in(&self) -> std::time::Duration {
-        if !self.never_expiring {
+        if let (false, Some(expiry)) = (self.never_expiring, self.system_expiry) {
+            expiry
+                .duration_since(std::time::SystemTime::now())
+                .unwrap_or_default()
+        } else if !self.never_expiring {
             self.expires_in
                 .checked_sub(self.struct_created.elapsed())
                 .unwrap_or_default()
@@ -327,9 +797,97 @@ impl TwitchToken for UserToken {
         }
     }
 
+    fn initial_expires_in(&self) -> Option<std::time::Duration> {
+        UserToken::initial_expires_in(self)
+    }
+
+    fn age(&self) -> Option<std::time::Duration> { Some(self.struct_created.elapsed()) }
+
+    fn never_expires(&self) -> bool { self.never_expiring }
+
+    fn expires_at(&self) -> Option<std::time::SystemTime> {
+        if !self.never_expiring {
+            self.system_expiry
+                .or_else(|| std::time::SystemTime::now().checked_add(self.expires_in()))
+        } else {
+            None
+        }
+    }
+
     fn scopes(&self) -> &[Scope] { self.scopes.as_slice() }
 }
 
+/// Builder for assembling a [`UserToken`] without checks, created with [`UserToken::unchecked_builder`]
+///
+/// The token has no scopes and never expires unless set otherwise.
+#[derive(Clone)]
+pub struct UncheckedUserTokenBuilder {
+    access_token: AccessToken,
+    client_id: ClientId,
+    login: UserName,
+    user_id: UserId,
+    client_secret: Option<ClientSecret>,
+    refresh_token: Option<RefreshToken>,
+    scopes: Vec<Scope>,
+    expires_in: Option<std::time::Duration>,
+    system_clock: bool,
+}
+
+impl UncheckedUserTokenBuilder {
+    /// Set the client secret, needed to refresh the token
+    pub fn client_secret(mut self, client_secret: impl Into<ClientSecret>) -> Self {
+        self.client_secret = Some(client_secret.into());
+        self
+    }
+
+    /// Set the refresh token
+    pub fn refresh_token(mut self, refresh_token: impl Into<RefreshToken>) -> Self {
+        self.refresh_token = Some(refresh_token.into());
+        self
+    }
+
+    /// Set the scopes of the token
+    pub fn scopes(mut self, scopes: Vec<Scope>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Set how long the token is valid for, from now
+    pub fn expires_in(mut self, expires_in: std::time::Duration) -> Self {
+        self.expires_in = Some(expires_in);
+        self
+    }
+
+    /// Track the expiry of the token with the system clock, instead of a monotonic [`Instant`](std::time::Instant).
+    ///
+    /// The expiry is then a fixed point in time, which matches what twitch uses, stays correct across suspend/resume and can be persisted as is.
+    /// The downside is that changes to the system clock, like manual changes or NTP corrections, change how long the token is considered valid.
+    pub fn with_system_clock(mut self) -> Self {
+        self.system_clock = true;
+        self
+    }
+
+    /// Assemble the token
+    pub fn build(self) -> UserToken {
+        let mut token = UserToken::from_existing_unchecked(
+            self.access_token,
+            self.refresh_token,
+            self.client_id,
+            self.client_secret,
+            self.login,
+            self.user_id,
+            Some(self.scopes),
+            self.expires_in,
+        );
+        if self.system_clock {
+            token.system_expiry = self
+                .expires_in
+                .and_then(|expires_in| std::time::SystemTime::now().checked_add(expires_in));
+        }
+        token
+    }
+}
+
 /// Builder for [OAuth authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow)
 ///
 /// See [`ImplicitUserTokenBuilder`] for the [OAuth implicit code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#implicit-grant-flow) (does not require Client Secret)
@@ -341,8 +899,8 @@ impl TwitchToken for UserToken {
 /// To generate a user token with this auth flow, you need to:
 ///
 /// 1. Initialize the [`UserTokenBuilder`] with [`UserTokenBuilder::new`](UserTokenBuilder::new), providing your client id, client secret, and a redirect URL.
-/// Use [`set_scopes(vec![])`](UserTokenBuilder::set_scopes) to add any necessary scopes to the request. You can also use [`force_verify(true)`](UserTokenBuilder::force_verify) to force the user to
-/// re-authorize your app’s access to their resources.
+///    Use [`set_scopes(vec![])`](UserTokenBuilder::set_scopes) to add any necessary scopes to the request. You can also use [`force_verify(true)`](UserTokenBuilder::force_verify) to force the user to
+///    re-authorize your app’s access to their resources.
 ///
 ///     Make sure you've added the redirect URL to the app settings on [the Twitch Developer Console](https://dev.twitch.tv/console).
 ///
@@ -374,11 +932,11 @@ impl TwitchToken for UserToken {
 /// 3. Have the user visit the generated URL. They will be asked to authorize your application if they haven't previously done so
 ///    or if you've set [`force_verify`](UserTokenBuilder::force_verify) to `true`.
 ///
-///      You can do this by providing the link in [a web page](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/a), have the user [be directed](https://developer.mozilla.org/en-US/docs/Web/API/Location/assign),
-///      the console, or by [opening it](https://docs.rs/webbrowser/0.8.10/webbrowser/) in a browser.
+///    You can do this by providing the link in [a web page](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/a), have the user [be directed](https://developer.mozilla.org/en-US/docs/Web/API/Location/assign),
+///    the console, or by [opening it](https://docs.rs/webbrowser/0.8.10/webbrowser/) in a browser.
 ///
-///      If this is a web server, you should store the [UserTokenBuilder] somewhere you can retrieve it later. A good place to store it is in a [`Cache`](https://docs.rs/retainer/0.3.0/retainer/cache/struct.Cache.html)
-///      or a [`HashMap`](std::collections::HashMap) with the CSRF token as the key.
+///    If this is a web server, you should store the [UserTokenBuilder] somewhere you can retrieve it later. A good place to store it is in a [`Cache`](https://docs.rs/retainer/0.3.0/retainer/cache/struct.Cache.html)
+///    or a [`HashMap`](std::collections::HashMap) with the CSRF token as the key.
 ///
 /// 4. When the user has been redirected to the redirect URL by twitch, extract the `state` and `code` query parameters from the URL.
 ///
@@ -435,7 +993,16 @@ pub struct UserTokenBuilder {
     pub(crate) scopes: Vec<Scope>,
     pub(crate) csrf: Option<crate::types::CsrfToken>,
     pub(crate) force_verify: bool,
+    pub(crate) sort_scopes: bool,
     pub(crate) redirect_url: url::Url,
+    pub(crate) pkce: Option<crate::types::PkceCodeVerifier>,
+    pub(crate) code: Option<String>,
+    pub(crate) form_body: bool,
+    pub(crate) openid_claims: Option<String>,
+    pub(crate) nonce: Option<String>,
+    pub(crate) extra_params: Vec<(String, String)>,
+    csrf_generator: Option<Box<dyn Fn() -> crate::types::CsrfToken + Send + Sync>>,
+    urls: crate::TwitchUrls,
     client_id: ClientId,
     client_secret: ClientSecret,
 }
@@ -451,29 +1018,140 @@ impl UserTokenBuilder {
     /// which means that you'll need to add `https://example.com/` to your redirect URIs (note the "trailing" slash) if you want to use an empty path.
     ///
     /// To avoid this, use a path such as `https://example.com/twitch/register` or similar instead, where the `url` crate would not add a trailing `/`.
+    /// Use [`canonical_redirect_url`](Self::canonical_redirect_url) to get the exact url that must be registered.
     pub fn new(
         client_id: impl Into<ClientId>,
         client_secret: impl Into<ClientSecret>,
         redirect_url: url::Url,
+    ) -> UserTokenBuilder {
+        UserTokenBuilder::new_with_urls(
+            client_id,
+            client_secret,
+            redirect_url,
+            crate::TwitchUrls::default(),
+        )
+    }
+
+    /// Create a [`UserTokenBuilder`] that uses `urls` instead of the twitch endpoints, see [`UserTokenBuilder::new`].
+    pub fn new_with_urls(
+        client_id: impl Into<ClientId>,
+        client_secret: impl Into<ClientSecret>,
+        redirect_url: url::Url,
+        urls: crate::TwitchUrls,
     ) -> UserTokenBuilder {
         UserTokenBuilder {
             scopes: vec![],
             csrf: None,
             force_verify: false,
+            sort_scopes: false,
             redirect_url,
+            pkce: None,
+            code: None,
+            form_body: false,
+            openid_claims: None,
+            nonce: None,
+            extra_params: vec![],
+            csrf_generator: None,
+            urls,
             client_id: client_id.into(),
             client_secret: client_secret.into(),
         }
     }
 
-    /// Add scopes to the request
-    pub fn set_scopes(mut self, scopes: Vec<Scope>) -> Self {
-        self.scopes = scopes;
-        self
-    }
-
-    /// Add a single scope to request
-    pub fn add_scope(&mut self, scope: Scope) { self.scopes.push(scope); }
+    /// Create a [`UserTokenBuilder`] with the client id and secret read from the `TWITCH_CLIENT_ID` and `TWITCH_CLIENT_SECRET` environment variables.
+    ///
+    /// See [`UserTokenBuilder::new`] for the requirements on `redirect_url`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use twitch_oauth2::tokens::UserTokenBuilder;
+    /// let redirect_url = url::Url::parse("http://localhost/twitch/register")?;
+    /// let builder = UserTokenBuilder::from_env(redirect_url)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_env(
+        redirect_url: url::Url,
+    ) -> Result<UserTokenBuilder, super::errors::FromEnvError> {
+        Ok(UserTokenBuilder::from_credentials(
+            crate::ClientCredentials::from_env()?,
+            redirect_url,
+        ))
+    }
+
+    /// Create a [`UserTokenBuilder`] from [`ClientCredentials`](crate::ClientCredentials), see [`UserTokenBuilder::new`].
+    pub fn from_credentials(
+        credentials: crate::ClientCredentials,
+        redirect_url: url::Url,
+    ) -> UserTokenBuilder {
+        UserTokenBuilder::new(
+            credentials.client_id,
+            credentials.client_secret,
+            redirect_url,
+        )
+    }
+
+    /// Add scopes to the request
+    pub fn set_scopes(mut self, scopes: Vec<Scope>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Add a single scope to request, if not already added
+    pub fn add_scope(&mut self, scope: Scope) {
+        if !self.scopes.contains(&scope) {
+            self.scopes.push(scope);
+        }
+    }
+
+    /// Add multiple scopes to request, skipping scopes already added
+    pub fn add_scopes(&mut self, scopes: impl IntoIterator<Item = Scope>) {
+        for scope in scopes {
+            self.add_scope(scope);
+        }
+    }
+
+    /// Set the redirect url to use.
+    ///
+    /// Useful for apps with multiple redirect urls registered, the url is used both in [`generate_url`](UserTokenBuilder::generate_url) and when exchanging the code.
+    /// Like in [`new`](UserTokenBuilder::new), the `redirect_url` must be present, verbatim, on [the Twitch Developer Console](https://dev.twitch.tv/console).
+    pub fn set_redirect_url(&mut self, redirect_url: url::Url) { self.redirect_url = redirect_url }
+
+    /// Get the redirect url exactly as it's sent to twitch, this is the url that must be present on [the Twitch Developer Console](https://dev.twitch.tv/console).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::tokens::UserTokenBuilder;
+    /// let redirect_url = url::Url::parse("https://example.com")?;
+    /// let builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url);
+    /// // note the trailing slash
+    /// assert_eq!(builder.canonical_redirect_url(), "https://example.com/");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn canonical_redirect_url(&self) -> &str { self.redirect_url.as_str() }
+
+    /// Check if the redirect url of this builder is one of the `registered` redirect urls, as entered on [the Twitch Developer Console](https://dev.twitch.tv/console).
+    ///
+    /// The registered urls are normalized the same way as the redirect url of this builder by parsing them with [`url::Url`],
+    /// so a registered `https://example.com` matches the [`canonical_redirect_url`](Self::canonical_redirect_url) `https://example.com/`.
+    /// Other differences, like a trailing slash on a non-empty path, still make them not match.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::tokens::UserTokenBuilder;
+    /// let redirect_url = url::Url::parse("https://example.com")?;
+    /// let builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url);
+    /// assert!(builder.check_redirect_registered(&[url::Url::parse("https://example.com")?]));
+    /// assert!(!builder.check_redirect_registered(&[url::Url::parse("http://example.com")?]));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn check_redirect_registered(&self, registered: &[url::Url]) -> bool {
+        registered
+            .iter()
+            .any(|url| url.as_str() == self.canonical_redirect_url())
+    }
 
     /// Enable or disable function to make the user able to switch accounts if needed.
     pub fn force_verify(mut self, b: bool) -> Self {
@@ -481,13 +1159,137 @@ impl UserTokenBuilder {
         self
     }
 
+    /// Sort the scopes in the generated url, making the url the same regardless of the order the scopes were added in. Defaults to false.
+    pub fn sort_scopes(mut self, b: bool) -> Self {
+        self.sort_scopes = b;
+        self
+    }
+
+    /// Set the function used to generate the CSRF token (the `state`) in [`generate_url`](UserTokenBuilder::generate_url). Defaults to [`CsrfToken::new_random`](crate::types::CsrfToken::new_random).
+    ///
+    /// Useful for deterministic tests, or to derive the `state` from something like a signed session id.
+    /// The generated token must be unguessable by others, or the `state` does not protect against CSRF.
+    pub fn set_csrf_generator(
+        mut self,
+        generator: impl Fn() -> crate::types::CsrfToken + Send + Sync + 'static,
+    ) -> Self {
+        self.csrf_generator = Some(Box::new(generator));
+        self
+    }
+
+    /// Add a parameter to the URL made by [`generate_url`](UserTokenBuilder::generate_url), for parameters not supported by this builder, like `lang`.
+    ///
+    /// Parameters are added after the ones set by the builder, and are not checked.
+    pub fn add_extra_param(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.extra_params.push((key.into(), value.into()));
+    }
+
+    /// Send the parameters when exchanging the code as an `application/x-www-form-urlencoded` body, instead of in the query. Defaults to false.
+    ///
+    /// This is the approach described in [RFC 6749](https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.3), and works with proxies that strip the query of `POST` requests.
+    ///
+    /// Twitch does not support HTTP Basic authentication for the client credentials, they are always sent as parameters.
+    pub fn form_body(mut self, b: bool) -> Self {
+        self.form_body = b;
+        self
+    }
+
+    /// Use [PKCE](https://datatracker.ietf.org/doc/html/rfc7636) for this request.
+    ///
+    /// Generates a code verifier, which is kept on the builder and sent when exchanging the code in [`get_user_token`](UserTokenBuilder::get_user_token).
+    /// The `S256` code challenge is added to the URL made by [`generate_url`](UserTokenBuilder::generate_url).
+    pub fn use_pkce(mut self) -> Self {
+        self.pkce = Some(crate::types::PkceCodeVerifier::new_random());
+        self
+    }
+
+    /// Request an [OpenID Connect](https://dev.twitch.tv/docs/authentication/getting-tokens-oidc/) ID token, adding the [`openid`](Scope::OpenId) scope.
+    ///
+    /// `claims` are the [claims](https://dev.twitch.tv/docs/authentication/getting-tokens-oidc/#requesting-claims) to include in the ID token, like `email` or `preferred_username`.
+    /// A random nonce is generated if none is set, which twitch includes in the ID token, see [`nonce`](UserTokenBuilder::nonce).
+    ///
+    /// The ID token is returned in [`TwitchTokenResponse::id_token`](crate::id::TwitchTokenResponse::id_token),
+    /// use [`get_user_token_response`](UserTokenBuilder::get_user_token_response) to get the response.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::tokens::UserTokenBuilder;
+    /// let redirect_url = url::Url::parse("http://localhost/twitch/register")?;
+    /// let builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url)
+    ///     .openid(&["email", "preferred_username"]);
+    /// let (url, _) = builder.authorize_url();
+    /// assert!(url
+    ///     .query_pairs()
+    ///     .any(|(k, v)| k == "scope" && v == "openid"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn openid(mut self, claims: &[&str]) -> Self {
+        self.add_scope(Scope::OpenId);
+        let claims: serde_json::Map<String, serde_json::Value> = claims
+            .iter()
+            .map(|claim| (claim.to_string(), serde_json::Value::Null))
+            .collect();
+        self.openid_claims = Some(serde_json::json!({ "id_token": claims }).to_string());
+        if self.nonce.is_none() {
+            self = self.use_nonce();
+        }
+        self
+    }
+
+    /// Send a random nonce in the URL made by [`generate_url`](UserTokenBuilder::generate_url).
+    ///
+    /// Twitch includes the nonce in the ID token, see [`openid`](UserTokenBuilder::openid).
+    pub fn use_nonce(mut self) -> Self {
+        self.nonce = Some(crate::types::CsrfToken::new_random().as_str().to_owned());
+        self
+    }
+
+    /// Set the nonce to send in the URL made by [`generate_url`](UserTokenBuilder::generate_url).
+    ///
+    /// Prefer [`use_nonce`](UserTokenBuilder::use_nonce), which generates a random nonce.
+    pub fn set_nonce(&mut self, nonce: impl Into<String>) { self.nonce = Some(nonce.into()) }
+
+    /// Get the nonce sent in the URL, see [`use_nonce`](UserTokenBuilder::use_nonce).
+    ///
+    /// Compare it with the [`nonce`](crate::id::IdTokenClaims::nonce) in the ID token to protect against replay attacks,
+    /// for example with [`verify_id_token`](crate::oidc::verify_id_token).
+    pub fn nonce(&self) -> Option<&str> { self.nonce.as_deref() }
+
     /// Generate the URL to request a code.
     ///
     /// First step in the [guide](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#get-the-user-to-authorize-your-app)
     pub fn generate_url(&mut self) -> (url::Url, crate::types::CsrfToken) {
-        let csrf = crate::types::CsrfToken::new_random();
+        let (url, csrf) = self.authorize_url();
         self.csrf = Some(csrf.clone());
-        let mut url = crate::AUTH_URL.clone();
+        (url, csrf)
+    }
+
+    /// Generate the URL to request a code, without storing the CSRF token in the builder.
+    ///
+    /// Useful for stateless servers, the CSRF token has to be stored by the caller and given back with [`with_csrf`](UserTokenBuilder::with_csrf) when the user is redirected back.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::tokens::UserTokenBuilder;
+    /// let redirect_url = url::Url::parse("http://localhost/twitch/register")?;
+    /// let builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url.clone());
+    /// let (url, csrf_token) = builder.authorize_url();
+    /// assert!(builder.csrf().is_none());
+    ///
+    /// // When the user is redirected back
+    /// let builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url)
+    ///     .with_csrf(csrf_token.clone());
+    /// assert!(builder.csrf_is_valid(csrf_token.secret()));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn authorize_url(&self) -> (url::Url, crate::types::CsrfToken) {
+        let csrf = self
+            .csrf_generator
+            .as_ref()
+            .map_or_else(crate::types::CsrfToken::new_random, |generate| generate());
+        let mut url = self.urls.auth_url.clone();
 
         let auth = vec![
             ("response_type", "code"),
@@ -499,14 +1301,34 @@ impl UserTokenBuilder {
         url.query_pairs_mut().extend_pairs(auth);
 
         if !self.scopes.is_empty() {
+            let mut scopes: Vec<&str> = self.scopes.iter().map(|s| s.as_str()).collect();
+            if self.sort_scopes {
+                scopes.sort_unstable();
+            }
             url.query_pairs_mut()
-                .append_pair("scope", &self.scopes.as_slice().join(" "));
+                .append_pair("scope", &scopes.join(" "));
         }
 
         if self.force_verify {
             url.query_pairs_mut().append_pair("force_verify", "true");
         };
 
+        if let Some(pkce) = &self.pkce {
+            url.query_pairs_mut()
+                .append_pair("code_challenge", &pkce.code_challenge())
+                .append_pair("code_challenge_method", "S256");
+        }
+
+        if let Some(claims) = &self.openid_claims {
+            url.query_pairs_mut().append_pair("claims", claims);
+        }
+
+        if let Some(nonce) = &self.nonce {
+            url.query_pairs_mut().append_pair("nonce", nonce);
+        }
+
+        url.query_pairs_mut().extend_pairs(&self.extra_params);
+
         (url, csrf)
     }
 
@@ -516,10 +1338,40 @@ impl UserTokenBuilder {
     #[doc(hidden)]
     pub fn set_csrf(&mut self, csrf: crate::types::CsrfToken) { self.csrf = Some(csrf); }
 
+    /// Use a CSRF token generated by a previous call to [`generate_url`](UserTokenBuilder::generate_url).
+    ///
+    /// The `state` passed to [`get_user_token`](UserTokenBuilder::get_user_token) is compared against this token.
+    /// This is useful for stateless servers, which can store the token (for example in a signed cookie) and rebuild the builder when the user is redirected back.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::tokens::UserTokenBuilder;
+    /// let redirect_url = url::Url::parse("http://localhost/twitch/register")?;
+    /// let mut builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url.clone());
+    /// let (url, csrf_token) = builder.generate_url();
+    /// // Store the csrf token and send the user to the url.
+    ///
+    /// // When the user is redirected back, rebuild the builder with the stored csrf token.
+    /// let builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url)
+    ///     .with_csrf(csrf_token.clone());
+    /// assert!(builder.csrf_is_valid(csrf_token.secret()));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_csrf(mut self, csrf: crate::types::CsrfToken) -> Self {
+        self.csrf = Some(csrf);
+        self
+    }
+
+    /// Get the CSRF token, if one has been generated or set.
+    pub fn csrf(&self) -> Option<&crate::types::CsrfToken> { self.csrf.as_ref() }
+
     /// Check if the CSRF is valid
+    ///
+    /// The comparison is done in constant time.
     pub fn csrf_is_valid(&self, csrf: &str) -> bool {
         if let Some(csrf2) = &self.csrf {
-            csrf2.secret() == csrf
+            csrf2.secret_eq(csrf)
         } else {
             false
         }
@@ -572,9 +1424,20 @@ impl UserTokenBuilder {
         params.insert("code", code);
         params.insert("grant_type", "authorization_code");
         params.insert("redirect_uri", self.redirect_url.as_str());
+        if let Some(pkce) = &self.pkce {
+            params.insert("code_verifier", pkce.secret());
+        }
 
+        if self.form_body {
+            return crate::construct_form_request(
+                &self.urls.token_url,
+                &params,
+                HeaderMap::new(),
+                Method::POST,
+            );
+        }
         crate::construct_request(
-            &crate::TOKEN_URL,
+            &self.urls.token_url,
             &params,
             HeaderMap::new(),
             Method::POST,
@@ -587,14 +1450,48 @@ impl UserTokenBuilder {
     /// Last step in the [guide](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#use-the-authorization-code-to-get-a-token)
     ///
     /// On failure to authenticate due to wrong redirect url or other errors, twitch redirects the user to `<redirect_url or first defined url in dev console>?error=<error type>&error_description=<description of error>`
+    ///
+    /// The user may not grant all requested scopes, use [`TwitchToken::missing_scopes`] on the returned token to check which were declined.
+    ///
+    /// See [`get_user_token_response`](UserTokenBuilder::get_user_token_response) to also get the response from twitch.
+    ///
+    /// `code` can be an [`AuthorizationCode`](crate::AuthorizationCode), a `String` or a `&str`.
     #[cfg(feature = "client")]
-    pub async fn get_user_token<'a, C>(
+    pub async fn get_user_token<C>(
         self,
-        http_client: &'a C,
+        http_client: &C,
         state: &str,
-        // TODO: Should be either str or AuthorizationCode
-        code: &str,
+        code: impl Into<crate::types::AuthorizationCode>,
     ) -> Result<UserToken, UserTokenExchangeError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        self.get_user_token_response(http_client, state, code)
+            .await
+            .map(|(token, _)| token)
+    }
+
+    /// Generate the code with the help of the authorization code, returning both the token and the [response](crate::id::TwitchTokenResponse) from twitch.
+    ///
+    /// Same as [`get_user_token`](UserTokenBuilder::get_user_token), useful to inspect or persist the exact response.
+    #[cfg(feature = "client")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(client_id = %self.client_id, scopes = ?self.scopes, status),
+            err(level = "warn")
+        )
+    )]
+    pub async fn get_user_token_response<C>(
+        self,
+        http_client: &C,
+        state: &str,
+        code: impl Into<crate::types::AuthorizationCode>,
+    ) -> Result<
+        (UserToken, crate::id::TwitchTokenResponse),
+        UserTokenExchangeError<<C as Client>::Error>,
+    >
     where
         C: Client,
     {
@@ -602,21 +1499,240 @@ impl UserTokenBuilder {
             return Err(UserTokenExchangeError::StateMismatch);
         }
 
+        self.exchange(http_client, code.into().secret()).await
+    }
+
+    /// Authenticate a user from a local application, like a CLI tool, in one call.
+    ///
+    /// Starts a small http server on `bind_addr`, opens the [generated url](UserTokenBuilder::generate_url) in the browser
+    /// and waits for twitch to redirect the user back. The `state` and `code` of the redirect are then used in [`get_user_token`](UserTokenBuilder::get_user_token).
+    ///
+    /// The redirect url of the builder has to point at `bind_addr`, e.g. `http://localhost:3000/callback` with `bind_addr` `127.0.0.1:3000`,
+    /// and be registered in the [developer console](https://dev.twitch.tv/console/apps/).
+    /// If the user doesn't finish authenticating within `timeout`, [`LocalServerError::Timeout`](super::errors::LocalServerError::Timeout) is returned.
+    /// The server is stopped when this returns or the future is dropped.
+    ///
+    /// If the browser can't be opened, [`LocalServerError::OpenBrowser`](super::errors::LocalServerError::OpenBrowser) contains the url.
+    /// Use [`authenticate_local_with`](UserTokenBuilder::authenticate_local_with) to show the url to the user instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .build()?;
+    /// # );
+    /// use twitch_oauth2::{tokens::UserTokenBuilder, url::Url, Scope};
+    ///
+    /// let redirect_url = Url::parse("http://localhost:3000/callback")?;
+    /// let mut builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url);
+    /// builder.add_scope(Scope::ChatRead);
+    /// let token = builder
+    ///     .authenticate_local(
+    ///         &client,
+    ///         "127.0.0.1:3000",
+    ///         std::time::Duration::from_secs(300),
+    ///     )
+    ///     .await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())};
+    /// ```
+    #[cfg(feature = "local-server")]
+    pub async fn authenticate_local<C>(
+        self,
+        http_client: &C,
+        bind_addr: impl std::net::ToSocketAddrs,
+        timeout: std::time::Duration,
+    ) -> Result<UserToken, super::errors::LocalServerError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        self.authenticate_local_with(
+            http_client,
+            bind_addr,
+            timeout,
+            super::local_server::open_browser,
+        )
+        .await
+    }
+
+    /// Authenticate a user from a local application, letting `open_url` send the user to the url.
+    ///
+    /// Same as [`authenticate_local`](UserTokenBuilder::authenticate_local), but instead of opening the browser, `open_url` is called with the url the user should visit.
+    /// An error from `open_url` is returned as [`LocalServerError::OpenBrowser`](super::errors::LocalServerError::OpenBrowser).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .build()?;
+    /// # );
+    /// use twitch_oauth2::{tokens::UserTokenBuilder, url::Url};
+    ///
+    /// let redirect_url = Url::parse("http://localhost:3000/callback")?;
+    /// let builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url);
+    /// let token = builder
+    ///     .authenticate_local_with(
+    ///         &client,
+    ///         "127.0.0.1:3000",
+    ///         std::time::Duration::from_secs(300),
+    ///         |url| {
+    ///             println!("Go to this page: {url}");
+    ///             Ok(())
+    ///         },
+    ///     )
+    ///     .await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())};
+    /// ```
+    #[cfg(feature = "local-server")]
+    pub async fn authenticate_local_with<C>(
+        mut self,
+        http_client: &C,
+        bind_addr: impl std::net::ToSocketAddrs,
+        timeout: std::time::Duration,
+        open_url: impl FnOnce(&url::Url) -> std::io::Result<()>,
+    ) -> Result<UserToken, super::errors::LocalServerError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        use super::errors::LocalServerError;
+        use super::local_server;
+
+        // Bind before opening the browser so the redirect can't arrive before the server is up.
+        let listener = std::net::TcpListener::bind(bind_addr)?;
+        let redirect = local_server::wait_for_redirect(
+            listener,
+            self.redirect_url.path().to_owned(),
+            timeout,
+        )?;
+        let (url, _) = self.generate_url();
+        if let Err(error) = open_url(&url) {
+            return Err(LocalServerError::OpenBrowser { url, error });
+        }
+
+        let callback = redirect.await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::TimedOut => LocalServerError::Timeout,
+            _ => e.into(),
+        })?;
+        let Some(code) = callback.code else {
+            return Err(LocalServerError::TwitchError {
+                error: callback.error,
+                description: callback.error_description,
+            });
+        };
+        Ok(self
+            .get_user_token(
+                http_client,
+                callback.state.as_deref().unwrap_or_default(),
+                code,
+            )
+            .await?)
+    }
+
+    /// Store the authorization code for a later exchange with [`complete_exchange`](UserTokenBuilder::complete_exchange).
+    ///
+    /// Use this when the code is received before the `state` can be verified, for example when it's delivered through `postMessage` in a browser.
+    pub fn prepare_exchange(&mut self, code: impl Into<String>) { self.code = Some(code.into()); }
+
+    /// Exchange the authorization code stored with [`prepare_exchange`](UserTokenBuilder::prepare_exchange) once the `state` has been verified.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # async move {
+    /// # use twitch_oauth2::tokens::UserTokenBuilder;
+    /// # let redirect_url = url::Url::parse("http://localhost/twitch/register")?;
+    /// # let mut builder = UserTokenBuilder::new("myclientid", "myclientsecret", redirect_url);
+    /// # let (url, csrf_token) = builder.generate_url();
+    /// # let client = twitch_oauth2::client::DummyClient; stringify!(
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .build()?;
+    /// # );
+    /// # let (code, state) = ("code", csrf_token.secret());
+    /// // The code arrives first
+    /// builder.prepare_exchange(code);
+    /// // ...and the state is verified later
+    /// let token = builder.complete_exchange(&client, state).await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    #[cfg(feature = "client")]
+    pub async fn complete_exchange<C>(
+        self,
+        http_client: &C,
+        verified_state: &str,
+    ) -> Result<UserToken, UserTokenExchangeError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        if !self.csrf_is_valid(verified_state) {
+            return Err(UserTokenExchangeError::StateMismatch);
+        }
+        let Some(code) = self.code.clone() else {
+            return Err(UserTokenExchangeError::NoCode);
+        };
+
+        self.exchange(http_client, &code)
+            .await
+            .map(|(token, _)| token)
+    }
+
+    #[cfg(feature = "client")]
+    async fn exchange<C>(
+        self,
+        http_client: &C,
+        code: &str,
+    ) -> Result<
+        (UserToken, crate::id::TwitchTokenResponse),
+        UserTokenExchangeError<<C as Client>::Error>,
+    >
+    where
+        C: Client,
+    {
         let req = self.get_user_token_request(code);
 
+        crate::trace_request(&req);
         let resp = http_client
             .req(req)
             .await
             .map_err(UserTokenExchangeError::RequestError)?;
+        crate::trace_response(&resp);
 
         let response = crate::id::TwitchTokenResponse::from_response(&resp)?;
-        let validated = response.access_token.validate_token(http_client).await?;
-
-        UserToken::from_response(response, validated, self.client_secret)
-            .map_err(|v| v.into_other().into())
+        let validated = response
+            .access_token
+            .validate_token_with_urls(http_client, &self.urls)
+            .await?;
+
+        let token = UserToken::from_response(response.clone(), validated, self.client_secret)
+            .map_err(|v| v.into_other())?;
+        Ok((token, response))
     }
 }
 
+/// Response from twitch in the redirect of the [OAuth implicit code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#implicit-grant-flow)
+///
+/// Parse with [`ImplicitUserTokenBuilder::parse_redirect_url`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ImplicitRedirect {
+    /// The access token, if the user authorized the application
+    pub access_token: Option<String>,
+    /// Scopes granted to the token
+    pub scopes: Vec<Scope>,
+    /// The state CSRF
+    pub state: Option<String>,
+    /// The type of the token, should be `bearer`
+    pub token_type: Option<String>,
+    /// Error type, if the authorization failed
+    pub error: Option<String>,
+    /// Description of the error, if the authorization failed
+    pub error_description: Option<String>,
+}
+
 /// Builder for [OAuth implicit code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#implicit-grant-flow)
 ///
 /// See [`UserTokenBuilder`] for the [OAuth authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow) (requires Client Secret, generally more secure)
@@ -625,7 +1741,14 @@ pub struct ImplicitUserTokenBuilder {
     pub(crate) csrf: Option<crate::types::CsrfToken>,
     pub(crate) redirect_url: url::Url,
     pub(crate) force_verify: bool,
+    pub(crate) sort_scopes: bool,
+    pub(crate) allow_missing_state: bool,
+    pub(crate) nonce: Option<String>,
+    pub(crate) extra_params: Vec<(String, String)>,
+    csrf_generator: Option<Box<dyn Fn() -> crate::types::CsrfToken + Send + Sync>>,
+    urls: crate::TwitchUrls,
     client_id: ClientId,
+    client_secret: Option<ClientSecret>,
 }
 
 impl ImplicitUserTokenBuilder {
@@ -639,24 +1762,80 @@ impl ImplicitUserTokenBuilder {
     /// which means that you'll need to add `https://example.com/` to your redirect URIs (note the "trailing" slash) if you want to use an empty path.
     ///
     /// To avoid this, use a path such as `https://example.com/twitch/register` or similar instead, where the `url` crate would not add a trailing `/`.
+    /// Use [`canonical_redirect_url`](Self::canonical_redirect_url) to get the exact url that must be registered.
     pub fn new(client_id: ClientId, redirect_url: url::Url) -> ImplicitUserTokenBuilder {
+        ImplicitUserTokenBuilder::new_with_urls(
+            client_id,
+            redirect_url,
+            crate::TwitchUrls::default(),
+        )
+    }
+
+    /// Create a [`ImplicitUserTokenBuilder`] that uses `urls` instead of the twitch endpoints, see [`ImplicitUserTokenBuilder::new`].
+    pub fn new_with_urls(
+        client_id: ClientId,
+        redirect_url: url::Url,
+        urls: crate::TwitchUrls,
+    ) -> ImplicitUserTokenBuilder {
         ImplicitUserTokenBuilder {
             scopes: vec![],
             redirect_url,
             csrf: None,
             force_verify: false,
+            sort_scopes: false,
+            allow_missing_state: false,
+            nonce: None,
+            extra_params: vec![],
+            csrf_generator: None,
+            urls,
             client_id,
+            client_secret: None,
         }
     }
 
+    /// Set the client secret to attach to the resulting [`UserToken`].
+    ///
+    /// The implicit flow does not use the client secret, but a confidential client completing the flow can attach it to the token.
+    ///
+    /// # Notes
+    ///
+    /// Tokens from the implicit flow have no refresh token, so the resulting [`UserToken`] can usually not be refreshed even with a client secret,
+    /// see [`UserToken::can_refresh`].
+    pub fn set_secret(&mut self, secret: Option<ClientSecret>) { self.client_secret = secret }
+
     /// Add scopes to the request
     pub fn set_scopes(mut self, scopes: Vec<Scope>) -> Self {
         self.scopes = scopes;
         self
     }
 
-    /// Add a single scope to request
-    pub fn add_scope(&mut self, scope: Scope) { self.scopes.push(scope); }
+    /// Get the redirect url exactly as it's sent to twitch, this is the url that must be present on [the Twitch Developer Console](https://dev.twitch.tv/console).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::tokens::ImplicitUserTokenBuilder;
+    /// let redirect_url = url::Url::parse("https://example.com")?;
+    /// let builder = ImplicitUserTokenBuilder::new("myclientid".into(), redirect_url);
+    /// // note the trailing slash
+    /// assert_eq!(builder.canonical_redirect_url(), "https://example.com/");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn canonical_redirect_url(&self) -> &str { self.redirect_url.as_str() }
+
+    /// Add a single scope to request, if not already added
+    pub fn add_scope(&mut self, scope: Scope) {
+        if !self.scopes.contains(&scope) {
+            self.scopes.push(scope);
+        }
+    }
+
+    /// Add multiple scopes to request, skipping scopes already added
+    pub fn add_scopes(&mut self, scopes: impl IntoIterator<Item = Scope>) {
+        for scope in scopes {
+            self.add_scope(scope);
+        }
+    }
 
     /// Enable or disable function to make the user able to switch accounts if needed.
     pub fn force_verify(mut self, b: bool) -> Self {
@@ -664,13 +1843,35 @@ impl ImplicitUserTokenBuilder {
         self
     }
 
+    /// Sort the scopes in the generated url, making the url the same regardless of the order the scopes were added in. Defaults to false.
+    pub fn sort_scopes(mut self, b: bool) -> Self {
+        self.sort_scopes = b;
+        self
+    }
+
+    /// Allow the `state` to be missing in [`get_user_token`](ImplicitUserTokenBuilder::get_user_token). Defaults to false.
+    ///
+    /// A `state` that is present is still checked.
+    ///
+    /// # Security
+    ///
+    /// Without the `state`, there is no protection against [CSRF](https://datatracker.ietf.org/doc/html/rfc6749#section-10.12),
+    /// an attacker could make the user authorize with a token of the attacker's choosing. Only enable this if you can't round-trip the `state`.
+    pub fn allow_missing_state(mut self, b: bool) -> Self {
+        self.allow_missing_state = b;
+        self
+    }
+
     /// Generate the URL to request a token.
     ///
     /// First step in the [guide](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#implicit-grant-flow)
     pub fn generate_url(&mut self) -> (url::Url, crate::types::CsrfToken) {
-        let csrf = crate::types::CsrfToken::new_random();
+        let csrf = self
+            .csrf_generator
+            .as_ref()
+            .map_or_else(crate::types::CsrfToken::new_random, |generate| generate());
         self.csrf = Some(csrf.clone());
-        let mut url = crate::AUTH_URL.clone();
+        let mut url = self.urls.auth_url.clone();
 
         let auth = vec![
             ("response_type", "token"),
@@ -682,26 +1883,148 @@ impl ImplicitUserTokenBuilder {
         url.query_pairs_mut().extend_pairs(auth);
 
         if !self.scopes.is_empty() {
+            let mut scopes: Vec<&str> = self.scopes.iter().map(|s| s.as_str()).collect();
+            if self.sort_scopes {
+                scopes.sort_unstable();
+            }
             url.query_pairs_mut()
-                .append_pair("scope", &self.scopes.as_slice().join(" "));
+                .append_pair("scope", &scopes.join(" "));
         }
 
         if self.force_verify {
             url.query_pairs_mut().append_pair("force_verify", "true");
         };
 
+        if let Some(nonce) = &self.nonce {
+            url.query_pairs_mut().append_pair("nonce", nonce);
+        }
+
+        url.query_pairs_mut().extend_pairs(&self.extra_params);
+
         (url, csrf)
     }
 
+    /// Set the function used to generate the CSRF token (the `state`) in [`generate_url`](ImplicitUserTokenBuilder::generate_url). Defaults to [`CsrfToken::new_random`](crate::types::CsrfToken::new_random).
+    ///
+    /// Useful for deterministic tests, or to derive the `state` from something like a signed session id.
+    /// The generated token must be unguessable by others, or the `state` does not protect against CSRF.
+    pub fn set_csrf_generator(
+        mut self,
+        generator: impl Fn() -> crate::types::CsrfToken + Send + Sync + 'static,
+    ) -> Self {
+        self.csrf_generator = Some(Box::new(generator));
+        self
+    }
+
+    /// Add a parameter to the URL made by [`generate_url`](ImplicitUserTokenBuilder::generate_url), for parameters not supported by this builder, like `lang`.
+    ///
+    /// Parameters are added after the ones set by the builder, and are not checked.
+    pub fn add_extra_param(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.extra_params.push((key.into(), value.into()));
+    }
+
+    /// Send a random nonce in the URL made by [`generate_url`](ImplicitUserTokenBuilder::generate_url).
+    pub fn use_nonce(mut self) -> Self {
+        self.nonce = Some(crate::types::CsrfToken::new_random().as_str().to_owned());
+        self
+    }
+
+    /// Set the nonce to send in the URL made by [`generate_url`](ImplicitUserTokenBuilder::generate_url).
+    ///
+    /// Prefer [`use_nonce`](ImplicitUserTokenBuilder::use_nonce), which generates a random nonce.
+    pub fn set_nonce(&mut self, nonce: impl Into<String>) { self.nonce = Some(nonce.into()) }
+
+    /// Get the nonce sent in the URL, see [`use_nonce`](ImplicitUserTokenBuilder::use_nonce).
+    ///
+    /// Compare it with the [`nonce`](crate::id::IdTokenClaims::nonce) in an ID token to protect against replay attacks.
+    pub fn nonce(&self) -> Option<&str> { self.nonce.as_deref() }
+
     /// Check if the CSRF is valid
+    ///
+    /// The comparison is done in constant time.
     pub fn csrf_is_valid(&self, csrf: &str) -> bool {
         if let Some(csrf2) = &self.csrf {
-            csrf2.secret() == csrf
+            csrf2.secret_eq(csrf)
         } else {
             false
         }
     }
 
+    /// Parse the url the user was redirected to after authorizing.
+    ///
+    /// The response is read from the fragment (`#`) of the url, or from the query (`?`) if there is no fragment,
+    /// as is the case when twitch redirects with an error.
+    ///
+    /// Use the result with [`get_user_token_from_redirect`](ImplicitUserTokenBuilder::get_user_token_from_redirect).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use twitch_oauth2::{tokens::ImplicitUserTokenBuilder, Scope};
+    ///
+    /// let url = url::Url::parse("http://localhost/twitch/register#access_token=token&scope=chat%3Aread+chat%3Aedit&state=state&token_type=bearer")?;
+    /// let redirect = ImplicitUserTokenBuilder::parse_redirect_url(&url);
+    /// assert_eq!(redirect.access_token.as_deref(), Some("token"));
+    /// assert_eq!(redirect.scopes, [Scope::ChatRead, Scope::ChatEdit]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn parse_redirect_url(url: &url::Url) -> ImplicitRedirect {
+        let pairs = match url.fragment() {
+            Some(fragment) if !fragment.is_empty() => fragment,
+            _ => url.query().unwrap_or_default(),
+        };
+        let mut redirect = ImplicitRedirect::default();
+        for (key, value) in url::form_urlencoded::parse(pairs.as_bytes()) {
+            let value = value.into_owned();
+            match key.as_ref() {
+                "access_token" => redirect.access_token = Some(value),
+                "scope" => redirect.scopes = Scope::parse_list(&value),
+                "state" => redirect.state = Some(value),
+                "token_type" => redirect.token_type = Some(value),
+                "error" => redirect.error = Some(value),
+                "error_description" => redirect.error_description = Some(value),
+                _ => {}
+            }
+        }
+        redirect
+    }
+
+    /// Generate the code with the help of a redirect parsed with [`parse_redirect_url`](ImplicitUserTokenBuilder::parse_redirect_url).
+    ///
+    /// The scopes of the token are the [scopes in the redirect](ImplicitRedirect::scopes) that are confirmed when validating the token,
+    /// which can be fewer than requested if the user unchecked some scopes when authorizing.
+    ///
+    /// See [`get_user_token`](ImplicitUserTokenBuilder::get_user_token)
+    #[cfg(feature = "client")]
+    pub async fn get_user_token_from_redirect<C>(
+        self,
+        http_client: &C,
+        redirect: &ImplicitRedirect,
+    ) -> Result<UserToken, ImplicitUserTokenExchangeError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let mut token = self
+            .get_user_token(
+                http_client,
+                redirect.state.as_deref(),
+                redirect.access_token.as_deref(),
+                redirect.error.as_deref(),
+                redirect.error_description.as_deref(),
+            )
+            .await?;
+        if !redirect.scopes.is_empty() {
+            // Only keep the scopes that twitch confirmed when validating
+            token.scopes = redirect
+                .scopes
+                .iter()
+                .filter(|scope| token.scopes.contains(scope))
+                .cloned()
+                .collect();
+        }
+        Ok(token)
+    }
+
     /// Generate the code with the help of the hash.
     ///
     /// You can skip this method and instead use the token in the hash directly with [`UserToken::from_existing()`], but it's provided here for convenience.
@@ -793,9 +2116,9 @@ impl ImplicitUserTokenBuilder {
     /// </html>
     /// ```
     #[cfg(feature = "client")]
-    pub async fn get_user_token<'a, C>(
+    pub async fn get_user_token<C>(
         self,
-        http_client: &'a C,
+        http_client: &C,
         state: Option<&str>,
         access_token: Option<&str>,
         error: Option<&str>,
@@ -804,16 +2127,20 @@ impl ImplicitUserTokenBuilder {
     where
         C: Client,
     {
-        if !state.map(|s| self.csrf_is_valid(s)).unwrap_or_default() {
+        if !state
+            .map(|s| self.csrf_is_valid(s))
+            .unwrap_or(self.allow_missing_state)
+        {
             return Err(ImplicitUserTokenExchangeError::StateMismatch);
         }
 
         match (access_token, error, error_description) {
-            (Some(access_token), None, None) => UserToken::from_existing(
+            (Some(access_token), None, None) => UserToken::from_existing_with_urls(
                 http_client,
                 crate::types::AccessToken::from(access_token),
                 None,
-                None,
+                self.client_secret,
+                &self.urls,
             )
             .await
             .map_err(Into::into),
@@ -828,12 +2155,233 @@ impl ImplicitUserTokenBuilder {
     }
 }
 
+/// Builder for [OAuth device code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#device-code-grant-flow)
+///
+/// Useful for devices that can't open a browser or receive a redirect, like CLI tools and TVs.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use twitch_oauth2::{DeviceUserTokenBuilder, Scope};
+/// # async {let client = twitch_oauth2::client::DummyClient; stringify!(
+/// let client = reqwest::Client::builder()
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()?;
+/// # );
+/// let mut builder = DeviceUserTokenBuilder::new("myclientid", vec![Scope::ChatRead]);
+/// let code = builder.start(&client).await?;
+/// println!(
+///     "Please go to {} and enter the code {}",
+///     code.verification_uri, code.user_code
+/// );
+/// let token = builder.poll(&client, tokio::time::sleep).await?;
+/// println!("User token: {:?}", token);
+/// # Ok::<(), Box<dyn std::error::Error>>(())};
+/// ```
+pub struct DeviceUserTokenBuilder {
+    pub(crate) scopes: Vec<Scope>,
+    pub(crate) response: Option<(std::time::Instant, crate::id::DeviceCodeResponse)>,
+    urls: crate::TwitchUrls,
+    client_id: ClientId,
+    client_secret: Option<ClientSecret>,
+}
+
+impl DeviceUserTokenBuilder {
+    /// Create a [`DeviceUserTokenBuilder`]
+    pub fn new(client_id: impl Into<ClientId>, scopes: Vec<Scope>) -> DeviceUserTokenBuilder {
+        DeviceUserTokenBuilder::new_with_urls(client_id, scopes, crate::TwitchUrls::default())
+    }
+
+    /// Create a [`DeviceUserTokenBuilder`] that uses `urls` instead of the twitch endpoints, see [`DeviceUserTokenBuilder::new`].
+    pub fn new_with_urls(
+        client_id: impl Into<ClientId>,
+        scopes: Vec<Scope>,
+        urls: crate::TwitchUrls,
+    ) -> DeviceUserTokenBuilder {
+        DeviceUserTokenBuilder {
+            scopes,
+            response: None,
+            urls,
+            client_id: client_id.into(),
+            client_secret: None,
+        }
+    }
+
+    /// Set the client secret, only needed for confidential clients.
+    ///
+    /// Without a client secret, the resulting [`UserToken`] can not be refreshed.
+    pub fn set_secret(&mut self, secret: Option<ClientSecret>) { self.client_secret = secret }
+
+    /// Get the device code response from [`start`](DeviceUserTokenBuilder::start), if the flow has been started.
+    pub fn device_code(&self) -> Option<&crate::id::DeviceCodeResponse> {
+        self.response.as_ref().map(|(_, response)| response)
+    }
+
+    /// Get the request for getting a [DeviceCodeResponse](crate::id::DeviceCodeResponse), to be used in [`DeviceUserTokenBuilder::get_user_token_request`].
+    ///
+    /// First step in the [guide](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#starting-the-dcf-flow-for-your-user)
+    pub fn get_device_code_request(&self) -> http::Request<Vec<u8>> {
+        use http::{HeaderMap, Method};
+        use std::collections::HashMap;
+        let scopes = self.scopes.as_slice().join(" ");
+        let mut params = HashMap::new();
+        params.insert("client_id", self.client_id.as_str());
+        params.insert("scopes", &scopes);
+
+        crate::construct_request(
+            &self.urls.device_url,
+            &params,
+            HeaderMap::new(),
+            Method::POST,
+            vec![],
+        )
+    }
+
+    /// Get the request for exchanging a device code for a [TwitchTokenResponse](crate::id::TwitchTokenResponse).
+    ///
+    /// Until the user has authorized the device, twitch will respond with `authorization_pending`.
+    pub fn get_user_token_request(&self, device_code: &str) -> http::Request<Vec<u8>> {
+        use http::{HeaderMap, Method};
+        use std::collections::HashMap;
+        let scopes = self.scopes.as_slice().join(" ");
+        let mut params = HashMap::new();
+        params.insert("client_id", self.client_id.as_str());
+        if let Some(client_secret) = &self.client_secret {
+            params.insert("client_secret", client_secret.secret());
+        }
+        params.insert("device_code", device_code);
+        params.insert("grant_type", "urn:ietf:params:oauth:grant-type:device_code");
+        params.insert("scopes", &scopes);
+
+        crate::construct_request(
+            &self.urls.token_url,
+            &params,
+            HeaderMap::new(),
+            Method::POST,
+            vec![],
+        )
+    }
+
+    /// Start the device code flow.
+    ///
+    /// Returns the codes the user needs to authorize this device, show [`user_code`](crate::id::DeviceCodeResponse::user_code)
+    /// and [`verification_uri`](crate::id::DeviceCodeResponse::verification_uri) to the user, then call [`poll`](DeviceUserTokenBuilder::poll).
+    #[cfg(feature = "client")]
+    pub async fn start<C>(
+        &mut self,
+        http_client: &C,
+    ) -> Result<&crate::id::DeviceCodeResponse, DeviceUserTokenExchangeError<<C as Client>::Error>>
+    where
+        C: Client,
+    {
+        let req = self.get_device_code_request();
+
+        let resp = http_client
+            .req(req)
+            .await
+            .map_err(DeviceUserTokenExchangeError::RequestError)?;
+
+        let response = match crate::id::DeviceCodeResponse::from_response(&resp) {
+            Ok(response) => response,
+            Err(crate::RequestParseError::TwitchError(error))
+                if error.kind() == crate::id::TwitchErrorKind::InvalidScope =>
+            {
+                return Err(DeviceUserTokenExchangeError::InvalidScope {
+                    requested_scopes: self.scopes.clone(),
+                    error,
+                })
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let (_, response) = self.response.insert((std::time::Instant::now(), response));
+        Ok(response)
+    }
+
+    /// Poll twitch until the user has authorized the device, waiting for the requested interval between each attempt.
+    ///
+    /// `sleep` is called with the duration to wait, use the sleep function of your async runtime, like [`tokio::time::sleep`](https://docs.rs/tokio/latest/tokio/time/fn.sleep.html).
+    ///
+    /// Last step in the [guide](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#device-code-grant-flow)
+    #[cfg(feature = "client")]
+    pub async fn poll<C, F, Fut>(
+        &self,
+        http_client: &C,
+        sleep: F,
+    ) -> Result<UserToken, DeviceUserTokenExchangeError<<C as Client>::Error>>
+    where
+        C: Client,
+        F: Fn(std::time::Duration) -> Fut,
+        Fut: std::future::Future<Output = ()>,
+    {
+        let (created, response) = self
+            .response
+            .as_ref()
+            .ok_or(DeviceUserTokenExchangeError::NoDeviceCode)?;
+        let mut interval = response.interval();
+        loop {
+            if created.elapsed() >= response.expires_in() {
+                return Err(DeviceUserTokenExchangeError::ExpiredToken);
+            }
+
+            let req = self.get_user_token_request(&response.device_code);
+            let resp = http_client
+                .req(req)
+                .await
+                .map_err(DeviceUserTokenExchangeError::RequestError)?;
+
+            match crate::id::TwitchTokenResponse::from_response(&resp) {
+                Ok(response) => {
+                    return UserToken::from_existing_with_urls(
+                        http_client,
+                        response.access_token,
+                        response.refresh_token,
+                        self.client_secret.clone(),
+                        &self.urls,
+                    )
+                    .await
+                    .map_err(Into::into)
+                }
+                Err(crate::RequestParseError::TwitchError(e)) => match e.kind() {
+                    crate::id::TwitchErrorKind::AuthorizationPending => (),
+                    // https://datatracker.ietf.org/doc/html/rfc8628#section-3.5
+                    crate::id::TwitchErrorKind::SlowDown => {
+                        interval += std::time::Duration::from_secs(5)
+                    }
+                    crate::id::TwitchErrorKind::AccessDenied => {
+                        return Err(DeviceUserTokenExchangeError::AccessDenied)
+                    }
+                    crate::id::TwitchErrorKind::InvalidGrant => {
+                        return Err(DeviceUserTokenExchangeError::ExpiredToken)
+                    }
+                    _ => return Err(crate::RequestParseError::TwitchError(e).into()),
+                },
+                Err(e) => return Err(e.into()),
+            }
+
+            sleep(interval).await;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::id::TwitchTokenResponse;
 
     pub use super::*;
 
+    #[test]
+    fn new_without_login() {
+        let validated = ValidatedToken {
+            client_id: "random_client".into(),
+            login: None,
+            user_id: None,
+            scopes: None,
+            expires_in: None,
+        };
+        let err = UserToken::new(AccessToken::from("token"), None, validated, None).unwrap_err();
+        assert!(matches!(err, ValidationError::NoLogin));
+    }
+
     #[test]
     fn from_validated_and_token() {
         let body = br#"
@@ -867,16 +2415,1459 @@ mod tests {
     }
 
     #[test]
-    fn generate_url() {
-        UserTokenBuilder::new(
+    fn from_response_without_validated_scopes() {
+        let body = br#"
+        {
+            "client_id": "wbmytr93xzw8zbg0p1izqyzzc5mbiz",
+            "login": "twitchdev",
+            "user_id": "141981764",
+            "expires_in": 5520838
+        }
+        "#;
+        let response = http::Response::builder().status(200).body(body).unwrap();
+        let validated = ValidatedToken::from_response(&response).unwrap();
+        let body = br#"
+        {
+            "access_token": "rfx2uswqe8l4g1mkagrvg5tv0ks3",
+            "expires_in": 14124,
+            "refresh_token": "5b93chm6hdve3mycz05zfzatkfdenfspp1h1ar2xxdalen01",
+            "scope": [
+                "channel:read:subscriptions"
+            ],
+            "token_type": "bearer"
+          }
+        "#;
+        let response = http::Response::builder().status(200).body(body).unwrap();
+        let response = TwitchTokenResponse::from_response(&response).unwrap();
+
+        let token = UserToken::from_response(response, validated, None).unwrap();
+        assert_eq!(token.scopes(), [Scope::ChannelReadSubscriptions]);
+    }
+
+    #[test]
+    fn satisfies() {
+        const REQUIRED: &[Scope] = &[Scope::ChatEdit, Scope::ChatRead];
+        let token = UserToken::from_existing_unchecked(
+            AccessToken::new("token".to_string()),
+            None,
+            ClientId::new("clientid".to_string()),
+            None,
+            "justintv".into(),
+            "1337".into(),
+            Some(vec![Scope::ChatRead, Scope::ChatEdit, Scope::WhispersRead]),
+            None,
+        );
+        assert!(token.satisfies(REQUIRED));
+        assert!(token.satisfies(&[]));
+        assert!(!token.satisfies(&[Scope::ChatEdit, Scope::UserReadEmail]));
+        assert!(token.has_scope(&Scope::WhispersRead));
+        assert!(!token.has_scope(&Scope::UserReadEmail));
+        assert_eq!(
+            token.missing_scopes(&[Scope::ChatEdit, Scope::UserReadEmail, Scope::ClipsEdit]),
+            [Scope::UserReadEmail, Scope::ClipsEdit]
+        );
+        assert!(token.missing_scopes(REQUIRED).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        use zeroize::Zeroize;
+
+        let mut token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .client_secret("secret")
+            .refresh_token("refresh")
+            .build();
+        token.zeroize();
+        assert_eq!(token.access_token.secret(), "");
+        assert_eq!(token.refresh_token.as_ref().unwrap().secret(), "");
+        assert_eq!(token.client_secret.as_ref().unwrap().secret(), "");
+        assert_eq!(token.user_id.as_str(), "1234");
+    }
+
+    #[test]
+    fn upgraded_from() {
+        let token = |scopes: Vec<Scope>| {
+            UserToken::unchecked_builder("token", "random_client", "user", "1234")
+                .scopes(scopes)
+                .build()
+        };
+        let old = token(vec![Scope::ChatRead]);
+
+        let new = old
+            .upgraded_from(token(vec![Scope::ChatRead, Scope::ChatEdit]))
+            .unwrap();
+        assert_eq!(new.scopes(), [Scope::ChatRead, Scope::ChatEdit]);
+
+        let err = old.upgraded_from(token(vec![Scope::ChatEdit])).unwrap_err();
+        assert_eq!(err.lost, [Scope::ChatRead]);
+        assert_eq!(err.token.scopes(), [Scope::ChatEdit]);
+        assert!(err
+            .to_string()
+            .starts_with("new token is missing scopes of the previous token"));
+    }
+
+    #[test]
+    fn serialize_token() {
+        let token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            RefreshToken::from("refresh"),
             ClientId::from("random_client"),
             ClientSecret::from("random_secret"),
-            url::Url::parse("https://localhost").unwrap(),
+            UserName::from("user"),
+            UserId::from("1234"),
+            Some(vec![Scope::ChatRead]),
+            Some(std::time::Duration::from_secs(3600)),
+        );
+        let json = serde_json::to_string(&token).unwrap();
+        assert!(!json.contains("random_secret"));
+        let token: UserToken = serde_json::from_str(&json).unwrap();
+        assert_eq!(token.access_token.secret(), "token");
+        assert_eq!(token.scopes(), &[Scope::ChatRead]);
+        assert!(token.client_secret.is_none());
+        assert!(!token.never_expiring);
+        assert!(token.expires_in() > std::time::Duration::from_secs(3590));
+        assert!(token.expires_in() <= std::time::Duration::from_secs(3600));
+        assert!(token.expires_at().unwrap() > std::time::SystemTime::now());
+        // The restored token expires at the exact persisted point in time
+        let expires_at = serde_json::from_str::<serde_json::Value>(&json).unwrap()["expires_at"]
+            .as_u64()
+            .unwrap();
+        assert_eq!(
+            token.expires_at(),
+            Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(expires_at))
+        );
+
+        let token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            ClientId::from("random_client"),
+            None,
+            UserName::from("user"),
+            UserId::from("1234"),
+            None,
+            None,
+        );
+        let token: UserToken =
+            serde_json::from_str(&serde_json::to_string(&token).unwrap()).unwrap();
+        assert!(token.never_expires());
+        assert!(token.expires_at().is_none());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn never_expiring_from_validation() {
+        use crate::client::MockClient;
+
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response("random_client", "justintv", "1337", &[], 0),
+        );
+        let token = UserToken::from_token(&client, AccessToken::from("token"))
+            .await
+            .unwrap();
+        assert!(token.never_expires());
+        assert!(!token.is_elapsed());
+        assert_eq!(token.expires_in(), std::time::Duration::MAX);
+        assert!(token.expires_at().is_none());
+        assert!(token.initial_expires_in().is_none());
+        assert!(!token.expires_within(std::time::Duration::MAX));
+    }
+
+    #[test]
+    fn unchecked_builder() {
+        let token = UserToken::unchecked_builder("token", "random_client", "user", "1234").build();
+        assert!(token.never_expires());
+        assert!(token.scopes().is_empty());
+        assert!(!token.can_refresh());
+
+        let token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .client_secret("secret")
+            .refresh_token("refresh")
+            .scopes(vec![Scope::ChatRead])
+            .expires_in(std::time::Duration::from_secs(3600))
+            .build();
+        assert!(!token.never_expires());
+        assert!(token.expires_in() <= std::time::Duration::from_secs(3600));
+        assert_eq!(token.scopes(), [Scope::ChatRead]);
+        assert!(token.scope_set().contains(&Scope::ChatRead));
+        assert!(token.can_refresh());
+        assert_eq!(token.login.as_str(), "user");
+    }
+
+    #[test]
+    fn with_system_clock() {
+        let token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .expires_in(std::time::Duration::from_secs(3600))
+            .with_system_clock()
+            .build();
+        let expires_at = token.expires_at().unwrap();
+        assert_eq!(token.expires_at(), Some(expires_at));
+        assert!(token.expires_in() <= std::time::Duration::from_secs(3600));
+        assert!(token.expires_in() > std::time::Duration::from_secs(3590));
+
+        let token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .with_system_clock()
+            .build();
+        assert!(token.never_expires());
+        assert!(token.expires_at().is_none());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn from_existing_with_retries() {
+        use crate::client::{Client, MockClient, MockClientError};
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        /// Fails the first `failures` requests
+        struct FlakyClient {
+            failures: AtomicU32,
+            inner: MockClient,
+        }
+
+        impl Client for FlakyClient {
+            type Error = MockClientError;
+
+            fn req(
+                &self,
+                request: http::Request<Vec<u8>>,
+            ) -> crate::client::BoxedFuture<'_, Result<http::Response<Vec<u8>>, Self::Error>>
+            {
+                if self
+                    .failures
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |f| f.checked_sub(1))
+                    .is_ok()
+                {
+                    let err = MockClientError::NoResponse(request.method().clone(), "flaky".into());
+                    return Box::pin(async { Err(err) });
+                }
+                self.inner.req(request)
+            }
+        }
+
+        let client = |failures| FlakyClient {
+            failures: AtomicU32::new(failures),
+            inner: MockClient::new().with_response(
+                http::Method::GET,
+                &crate::VALIDATE_URL,
+                MockClient::validate_response("random_client", "justintv", "1337", &[], 3600),
+            ),
+        };
+
+        let err = UserToken::from_existing(&client(1), AccessToken::from("token"), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ValidationError::Request(_)));
+
+        let token = UserToken::from_existing_with_retries(
+            &client(1),
+            AccessToken::from("token"),
+            None,
+            None,
+            1,
         )
-        .force_verify(true)
-        .generate_url()
-        .0
-        .to_string();
+        .await
+        .unwrap();
+        assert_eq!(token.login.as_str(), "justintv");
+
+        let err = UserToken::from_existing_with_retries(
+            &client(2),
+            AccessToken::from("token"),
+            None,
+            None,
+            1,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ValidationError::Request(_)));
+
+        // Responses from twitch are not retried
+        let client = FlakyClient {
+            failures: AtomicU32::new(0),
+            inner: MockClient::new().with_response(
+                http::Method::GET,
+                &crate::VALIDATE_URL,
+                MockClient::error_response(http::StatusCode::UNAUTHORIZED, "invalid access token"),
+            ),
+        };
+        let err = UserToken::from_existing_with_retries(
+            &client,
+            AccessToken::from("token"),
+            None,
+            None,
+            3,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ValidationError::NotAuthorized));
+    }
+
+    #[test]
+    fn eq_by_user() {
+        let token = |access_token: &str, user_id: &str| {
+            UserToken::unchecked_builder(access_token, "random_client", "user", user_id).build()
+        };
+        assert_eq!(token("token", "1234"), token("other_token", "1234"));
+        assert_ne!(token("token", "1234"), token("token", "4321"));
+        assert_ne!(
+            token("token", "1234"),
+            UserToken::unchecked_builder("token", "other_client", "user", "1234").build()
+        );
+
+        let mut tokens = std::collections::HashSet::new();
+        assert!(tokens.insert(token("token", "1234")));
+        assert!(!tokens.insert(token("refreshed_token", "1234")));
+        assert!(tokens.insert(token("token", "4321")));
+    }
+
+    #[test]
+    fn can_refresh() {
+        let mut token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            RefreshToken::from("refresh"),
+            ClientId::from("random_client"),
+            None,
+            UserName::from("user"),
+            UserId::from("1234"),
+            None,
+            None,
+        );
+        assert!(!token.can_refresh());
+        token.set_secret(Some(ClientSecret::from("secret")));
+        assert!(token.can_refresh());
+        token.refresh_token = None;
+        assert!(!token.can_refresh());
+    }
+
+    #[test]
+    fn initial_expires_in() {
+        let token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            ClientId::from("random_client"),
+            None,
+            UserName::from("user"),
+            UserId::from("1234"),
+            None,
+            Some(std::time::Duration::from_secs(3600)),
+        );
+        assert_eq!(
+            token.initial_expires_in(),
+            Some(std::time::Duration::from_secs(3600))
+        );
+        assert!(token.created_at() <= std::time::Instant::now());
+        assert!(token.age().unwrap() < std::time::Duration::from_secs(60));
+        assert!(token.expires_in() <= std::time::Duration::from_secs(3600));
+        assert!(token.expires_within(std::time::Duration::from_secs(3600)));
+        assert!(!token.expires_within(std::time::Duration::from_secs(60)));
+        assert!(!token.is_elapsed());
+        let remaining = token.lifetime_fraction_remaining().unwrap();
+        assert!(remaining > 0.99 && remaining <= 1.0);
+
+        let token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            ClientId::from("random_client"),
+            None,
+            UserName::from("user"),
+            UserId::from("1234"),
+            None,
+            None,
+        );
+        assert!(token.initial_expires_in().is_none());
+        assert!(token.lifetime_fraction_remaining().is_none());
+    }
+
+    #[test]
+    fn refresh_instant_with_jitter() {
+        use rand::SeedableRng as _;
+
+        let token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            ClientId::from("random_client"),
+            None,
+            UserName::from("user"),
+            UserId::from("1234"),
+            None,
+            Some(std::time::Duration::from_secs(3600)),
+        );
+        let expires = token.struct_created + std::time::Duration::from_secs(3600);
+        let window = std::time::Duration::from_secs(300);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1337);
+        let instant = token
+            .refresh_instant_with_jitter_rng(window, &mut rng)
+            .unwrap();
+        assert!(instant <= expires && instant >= expires - window);
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1337);
+        assert_eq!(
+            token.refresh_instant_with_jitter_rng(window, &mut rng),
+            Some(instant)
+        );
+
+        let token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            ClientId::from("random_client"),
+            None,
+            UserName::from("user"),
+            UserId::from("1234"),
+            None,
+            None,
+        );
+        assert!(token.refresh_instant_with_jitter(window).is_none());
+    }
+
+    #[test]
+    fn generate_url() {
+        UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        )
+        .force_verify(true)
+        .generate_url()
+        .0
+        .to_string();
+    }
+
+    #[test]
+    fn openid() {
+        let builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        )
+        .set_scopes(vec![Scope::ChatRead])
+        .openid(&["email", "email_verified"]);
+        assert_eq!(builder.scopes, [Scope::ChatRead, Scope::OpenId]);
+        let (url, _) = builder.authorize_url();
+        let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["scope"], "chat:read openid");
+        assert_eq!(params["nonce"], builder.nonce().unwrap());
+        let claims: serde_json::Value = serde_json::from_str(&params["claims"]).unwrap();
+        assert_eq!(
+            claims,
+            serde_json::json!({"id_token": {"email": null, "email_verified": null}})
+        );
+    }
+
+    #[test]
+    fn csrf_generator() {
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        )
+        .set_csrf_generator(|| crate::CsrfToken::from("session_state"));
+        let (url, csrf) = builder.generate_url();
+        assert_eq!(csrf.secret(), "session_state");
+        assert!(url
+            .query_pairs()
+            .any(|(k, v)| k == "state" && v == "session_state"));
+        assert!(builder.csrf_is_valid("session_state"));
+
+        let mut builder = ImplicitUserTokenBuilder::new(
+            ClientId::from("random_client"),
+            url::Url::parse("https://localhost").unwrap(),
+        )
+        .set_csrf_generator(|| crate::CsrfToken::from("session_state"));
+        let (_, csrf) = builder.generate_url();
+        assert_eq!(csrf.secret(), "session_state");
+    }
+
+    #[test]
+    fn sort_scopes() {
+        let url = |scopes: Vec<Scope>| {
+            let mut builder = UserTokenBuilder::new(
+                ClientId::from("random_client"),
+                ClientSecret::from("random_secret"),
+                url::Url::parse("https://localhost").unwrap(),
+            )
+            .set_scopes(scopes)
+            .sort_scopes(true)
+            .set_csrf_generator(|| crate::CsrfToken::from("state"));
+            builder.generate_url().0
+        };
+        assert_eq!(
+            url(vec![Scope::ChatRead, Scope::BitsRead, Scope::ChatEdit]),
+            url(vec![Scope::ChatEdit, Scope::ChatRead, Scope::BitsRead])
+        );
+        assert!(url(vec![Scope::ChatRead, Scope::BitsRead])
+            .query_pairs()
+            .any(|(k, v)| k == "scope" && v == "bits:read chat:read"));
+    }
+
+    #[test]
+    fn extra_params() {
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.add_extra_param("lang", "de");
+        let (url, _) = builder.authorize_url();
+        assert!(url.query_pairs().any(|(k, v)| k == "lang" && v == "de"));
+
+        let mut builder = ImplicitUserTokenBuilder::new(
+            ClientId::from("random_client"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.add_extra_param("lang", "de");
+        let (url, _) = builder.generate_url();
+        assert!(url.query_pairs().any(|(k, v)| k == "lang" && v == "de"));
+    }
+
+    #[test]
+    fn nonce() {
+        let nonce = |url: url::Url| {
+            url.query_pairs()
+                .find(|(k, _)| k == "nonce")
+                .map(|(_, v)| v.into_owned())
+        };
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        assert_eq!(nonce(builder.authorize_url().0), None);
+        builder.set_nonce("my_nonce");
+        assert_eq!(
+            nonce(builder.authorize_url().0).as_deref(),
+            Some("my_nonce")
+        );
+        // openid keeps an existing nonce
+        let builder = builder.openid(&[]);
+        assert_eq!(builder.nonce(), Some("my_nonce"));
+
+        let mut builder = ImplicitUserTokenBuilder::new(
+            ClientId::from("random_client"),
+            url::Url::parse("https://localhost").unwrap(),
+        )
+        .use_nonce();
+        let generated = builder.nonce().unwrap().to_owned();
+        assert_eq!(nonce(builder.generate_url().0), Some(generated));
+    }
+
+    #[test]
+    fn form_body() {
+        let builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        let req = builder.get_user_token_request("authcode");
+        assert!(req.uri().query().unwrap().contains("code=authcode"));
+        assert!(req.body().is_empty());
+
+        let req = builder.form_body(true).get_user_token_request("authcode");
+        assert_eq!(req.uri().query(), None);
+        assert_eq!(
+            req.headers()[http::header::CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        let params: std::collections::HashMap<_, _> = url::form_urlencoded::parse(req.body())
+            .into_owned()
+            .collect();
+        assert_eq!(params["code"], "authcode");
+        assert_eq!(params["client_secret"], "random_secret");
+        assert_eq!(params["grant_type"], "authorization_code");
+        assert_eq!(params["redirect_uri"], "https://localhost/");
+    }
+
+    #[test]
+    fn check_redirect_registered() {
+        let parse = |urls: &[&str]| -> Vec<url::Url> {
+            urls.iter()
+                .map(|url| url::Url::parse(url).unwrap())
+                .collect()
+        };
+        let builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://example.com").unwrap(),
+        );
+        assert!(!builder.check_redirect_registered(&[]));
+        // An empty path gets a trailing slash, whether it's registered with one or not.
+        assert!(builder.check_redirect_registered(&parse(&["https://example.com"])));
+        assert!(builder.check_redirect_registered(&parse(&["https://example.com/"])));
+        assert!(builder
+            .check_redirect_registered(&parse(&["http://localhost", "HTTPS://EXAMPLE.COM:443"])));
+        assert!(!builder.check_redirect_registered(&parse(&["http://example.com"])));
+
+        let builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://example.com/twitch/register").unwrap(),
+        );
+        assert!(builder.check_redirect_registered(&parse(&["https://example.com/twitch/register"])));
+        assert!(
+            !builder.check_redirect_registered(&parse(&["https://example.com/twitch/register/"]))
+        );
+    }
+
+    #[test]
+    fn set_redirect_url() {
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost/first").unwrap(),
+        );
+        let redirect_uri = |url: url::Url| {
+            url.query_pairs()
+                .find(|(k, _)| k == "redirect_uri")
+                .map(|(_, v)| v.into_owned())
+        };
+        let first = redirect_uri(builder.generate_url().0);
+        builder.set_redirect_url(url::Url::parse("https://localhost/second").unwrap());
+        let second = redirect_uri(builder.generate_url().0);
+        assert_eq!(first.as_deref(), Some("https://localhost/first"));
+        assert_eq!(second.as_deref(), Some("https://localhost/second"));
+    }
+
+    #[test]
+    fn device_code_request() {
+        let builder = DeviceUserTokenBuilder::new(
+            ClientId::from("random_client"),
+            vec![Scope::ChatRead, Scope::ChatEdit],
+        );
+        let req = builder.get_device_code_request();
+        assert_eq!(req.method(), http::Method::POST);
+        let url = url::Url::parse(&req.uri().to_string()).unwrap();
+        assert_eq!(url.path(), "/oauth2/device");
+        assert!(url
+            .query_pairs()
+            .any(|(k, v)| k == "scopes" && v == "chat:read chat:edit"));
+
+        let req = builder.get_user_token_request("device_code");
+        let url = url::Url::parse(&req.uri().to_string()).unwrap();
+        assert!(
+            url.query_pairs()
+                .any(|(k, v)| k == "grant_type"
+                    && v == "urn:ietf:params:oauth:grant-type:device_code")
+        );
+        assert!(!url.query_pairs().any(|(k, _)| k == "client_secret"));
+    }
+
+    #[test]
+    fn builders_use_urls() {
+        let urls =
+            crate::TwitchUrls::from_root(&url::Url::parse("http://localhost:8080/auth/").unwrap())
+                .unwrap();
+        let path = |req: http::Request<Vec<u8>>| {
+            url::Url::parse(&req.uri().to_string())
+                .unwrap()
+                .path()
+                .to_owned()
+        };
+
+        let builder = UserTokenBuilder::new_with_urls(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+            urls.clone(),
+        );
+        assert_eq!(builder.authorize_url().0.path(), "/auth/authorize");
+        assert_eq!(path(builder.get_user_token_request("code")), "/auth/token");
+
+        let mut builder = ImplicitUserTokenBuilder::new_with_urls(
+            ClientId::from("random_client"),
+            url::Url::parse("https://localhost").unwrap(),
+            urls.clone(),
+        );
+        assert_eq!(builder.generate_url().0.path(), "/auth/authorize");
+
+        let builder =
+            DeviceUserTokenBuilder::new_with_urls(ClientId::from("random_client"), vec![], urls);
+        assert_eq!(path(builder.get_device_code_request()), "/auth/device");
+        assert_eq!(path(builder.get_user_token_request("code")), "/auth/token");
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn get_user_token_with_urls() {
+        use crate::client::MockClient;
+
+        let urls =
+            crate::TwitchUrls::from_root(&url::Url::parse("http://localhost:8080/auth/").unwrap())
+                .unwrap();
+        // Only the custom urls respond, a request to twitch fails
+        let client = MockClient::new()
+            .with_response(
+                http::Method::POST,
+                &urls.token_url,
+                MockClient::token_response("token", Some("refresh"), &[], 3600),
+            )
+            .with_response(
+                http::Method::GET,
+                &urls.validate_url,
+                MockClient::validate_response("random_client", "justintv", "1337", &[], 3600),
+            );
+        let mut builder = UserTokenBuilder::new_with_urls(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+            urls.clone(),
+        );
+        builder.set_csrf(crate::CsrfToken::from("random"));
+        let token = builder
+            .get_user_token(&client, "random", "authcode")
+            .await
+            .unwrap();
+        assert_eq!(token.login.as_str(), "justintv");
+
+        let (access_token, ..) = token
+            .refresh_token
+            .as_ref()
+            .unwrap()
+            .refresh_token_with_urls(
+                &client,
+                &token.client_id,
+                &ClientSecret::from("random_secret"),
+                &urls,
+            )
+            .await
+            .unwrap();
+        assert_eq!(access_token.secret(), "token");
+        assert!(access_token.validate_token(&client).await.is_err());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn device_poll_error_kind() {
+        use crate::client::MockClient;
+
+        let body = br#"
+        {
+            "device_code": "ike3GM8QIdYZs43KdrWPIO36LofILoCyFEzjlQ91",
+            "expires_in": 1800,
+            "interval": 5,
+            "user_code": "ABCDEFGH",
+            "verification_uri": "https://www.twitch.tv/activate?public=true&device-code=ABCDEFGH"
+        }
+        "#;
+        let response = http::Response::builder().status(200).body(body).unwrap();
+        let response = crate::id::DeviceCodeResponse::from_response(&response).unwrap();
+        let mut builder = DeviceUserTokenBuilder::new(ClientId::from("random_client"), vec![]);
+        builder.response = Some((std::time::Instant::now(), response));
+
+        let poll = |message: &'static str| {
+            let client = MockClient::new().with_response(
+                http::Method::POST,
+                &crate::TOKEN_URL,
+                MockClient::error_response(http::StatusCode::BAD_REQUEST, message),
+            );
+            let builder = &builder;
+            async move { builder.poll(&client, |_| async {}).await.unwrap_err() }
+        };
+        assert!(matches!(
+            poll("Invalid device code").await,
+            DeviceUserTokenExchangeError::ExpiredToken
+        ));
+        assert!(matches!(
+            poll("access_denied").await,
+            DeviceUserTokenExchangeError::AccessDenied
+        ));
+    }
+
+    #[test]
+    fn device_code_response() {
+        let body = br#"
+        {
+            "device_code": "ike3GM8QIdYZs43KdrWPIO36LofILoCyFEzjlQ91",
+            "expires_in": 1800,
+            "interval": 5,
+            "user_code": "ABCDEFGH",
+            "verification_uri": "https://www.twitch.tv/activate?public=true&device-code=ABCDEFGH"
+        }
+        "#;
+        let response = http::Response::builder().status(200).body(body).unwrap();
+        let response = crate::id::DeviceCodeResponse::from_response(&response).unwrap();
+        assert_eq!(response.interval(), std::time::Duration::from_secs(5));
+        assert_eq!(response.user_code, "ABCDEFGH");
+    }
+
+    #[test]
+    fn with_csrf() {
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        assert!(builder.csrf().is_none());
+        let (_, csrf) = builder.generate_url();
+        assert_eq!(builder.csrf(), Some(&csrf));
+
+        let builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        )
+        .with_csrf(csrf.clone());
+        assert!(builder.csrf_is_valid(csrf.secret()));
+        assert!(!builder.csrf_is_valid("other"));
+    }
+
+    #[test]
+    fn add_scopes() {
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.add_scope(Scope::ChatRead);
+        builder.add_scopes([Scope::ChatEdit, Scope::ChatRead, Scope::ChatEdit]);
+        assert_eq!(builder.scopes, [Scope::ChatRead, Scope::ChatEdit]);
+
+        let mut builder = ImplicitUserTokenBuilder::new(
+            ClientId::from("random_client"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.add_scopes(Scope::chat_read_write());
+        builder.add_scopes(Scope::chat_read_write());
+        assert_eq!(builder.scopes, Scope::chat_read_write());
+    }
+
+    #[test]
+    fn generate_url_pkce() {
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        let (url, _) = builder.generate_url();
+        assert!(!url.query_pairs().any(|(k, _)| k == "code_challenge"));
+        let req = builder.get_user_token_request("code");
+        assert!(!req.uri().to_string().contains("code_verifier"));
+
+        let mut builder = builder.use_pkce();
+        let (url, _) = builder.generate_url();
+        let challenge = builder.pkce.as_ref().unwrap().code_challenge();
+        assert!(url
+            .query_pairs()
+            .any(|(k, v)| k == "code_challenge" && v == challenge));
+        assert!(url
+            .query_pairs()
+            .any(|(k, v)| k == "code_challenge_method" && v == "S256"));
+        let req = builder.get_user_token_request("code");
+        let url = url::Url::parse(&req.uri().to_string()).unwrap();
+        assert!(url
+            .query_pairs()
+            .any(|(k, v)| k == "code_verifier" && v == builder.pkce.as_ref().unwrap().secret()));
+    }
+
+    #[test]
+    fn parse_implicit_redirect() {
+        let url = url::Url::parse("http://localhost/twitch/register#access_token=token&scope=chat%3Aread+chat%3Aedit&state=random&token_type=bearer").unwrap();
+        let redirect = ImplicitUserTokenBuilder::parse_redirect_url(&url);
+        assert_eq!(redirect.access_token.as_deref(), Some("token"));
+        assert_eq!(redirect.scopes, [Scope::ChatRead, Scope::ChatEdit]);
+        assert_eq!(redirect.state.as_deref(), Some("random"));
+        assert_eq!(redirect.token_type.as_deref(), Some("bearer"));
+        assert!(redirect.error.is_none());
+
+        let url = url::Url::parse("http://localhost/twitch/register?error=access_denied&error_description=The+user+denied+you+access&state=random").unwrap();
+        let redirect = ImplicitUserTokenBuilder::parse_redirect_url(&url);
+        assert!(redirect.access_token.is_none());
+        assert_eq!(redirect.error.as_deref(), Some("access_denied"));
+        assert_eq!(
+            redirect.error_description.as_deref(),
+            Some("The user denied you access")
+        );
+        assert_eq!(redirect.state.as_deref(), Some("random"));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn implicit_redirect_scopes() {
+        use crate::client::MockClient;
+
+        let mut builder = ImplicitUserTokenBuilder::new(
+            ClientId::from("random_client"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.csrf = Some(crate::CsrfToken::from("random"));
+
+        // Validation returns the scopes in a different order than the redirect
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response(
+                "random_client",
+                "justintv",
+                "1337",
+                &[Scope::ChatEdit, Scope::ChatRead],
+                3600,
+            ),
+        );
+        let url = url::Url::parse("https://localhost/#access_token=token&scope=chat:read%20chat:edit&state=random&token_type=bearer").unwrap();
+        let redirect = ImplicitUserTokenBuilder::parse_redirect_url(&url);
+        assert_eq!(redirect.scopes, [Scope::ChatRead, Scope::ChatEdit]);
+        let token = builder
+            .get_user_token_from_redirect(&client, &redirect)
+            .await
+            .unwrap();
+        assert_eq!(token.scopes(), [Scope::ChatRead, Scope::ChatEdit]);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn refresh_updates_scopes() {
+        use crate::client::MockClient;
+
+        let token = || {
+            UserToken::unchecked_builder("token", "random_client", "user", "1234")
+                .client_secret("secret")
+                .refresh_token("refresh")
+                .scopes(vec![Scope::ChatRead, Scope::ChatEdit])
+                .expires_in(std::time::Duration::from_secs(10))
+                .build()
+        };
+
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("new_token", Some("new_refresh"), &[Scope::ChatRead], 3600),
+        );
+        let mut refreshed = token();
+        refreshed.refresh_token(&client).await.unwrap();
+        assert_eq!(refreshed.access_token.secret(), "new_token");
+        assert_eq!(refreshed.scopes(), [Scope::ChatRead]);
+
+        // Without a scope in the response, the token is validated to get the scopes.
+        let client = MockClient::new()
+            .with_response(
+                http::Method::POST,
+                &crate::TOKEN_URL,
+                http::Response::builder()
+                    .status(http::StatusCode::OK)
+                    .body(
+                        br#"{"access_token":"new_token","refresh_token":"new_refresh","expires_in":3600,"token_type":"bearer"}"#
+                            .to_vec(),
+                    )
+                    .unwrap(),
+            )
+            .with_response(
+                http::Method::GET,
+                &crate::VALIDATE_URL,
+                MockClient::validate_response(
+                    "random_client",
+                    "user",
+                    "1234",
+                    &[Scope::ChatEdit],
+                    3600,
+                ),
+            );
+        let mut refreshed = token();
+        refreshed.refresh_token(&client).await.unwrap();
+        assert_eq!(refreshed.scopes(), [Scope::ChatEdit]);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn refresh_updates_expiry() {
+        use crate::client::MockClient;
+
+        let token = || {
+            UserToken::unchecked_builder("token", "random_client", "user", "1234")
+                .client_secret("secret")
+                .refresh_token("refresh")
+                .expires_in(std::time::Duration::from_secs(10))
+                .build()
+        };
+        let response = |expires_in: &str| {
+            http::Response::builder()
+                .body(
+                    format!(r#"{{"access_token":"new_token","refresh_token":"new_refresh","scope":[]{expires_in}}}"#)
+                        .into_bytes(),
+                )
+                .unwrap()
+        };
+
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            response(r#","expires_in":3600"#),
+        );
+        let mut refreshed = token();
+        refreshed.refresh_token(&client).await.unwrap();
+        assert_eq!(
+            refreshed.initial_expires_in(),
+            Some(std::time::Duration::from_secs(3600))
+        );
+
+        // Without an expiry in the response, the token is validated to get it.
+        let client = MockClient::new()
+            .with_response(http::Method::POST, &crate::TOKEN_URL, response(""))
+            .with_response(
+                http::Method::GET,
+                &crate::VALIDATE_URL,
+                MockClient::validate_response("random_client", "user", "1234", &[], 1800),
+            );
+        let mut refreshed = token();
+        refreshed.refresh_token(&client).await.unwrap();
+        assert_eq!(
+            refreshed.initial_expires_in(),
+            Some(std::time::Duration::from_secs(1800))
+        );
+
+        let client = MockClient::new()
+            .with_response(http::Method::POST, &crate::TOKEN_URL, response(""))
+            .with_response(
+                http::Method::GET,
+                &crate::VALIDATE_URL,
+                MockClient::validate_response("random_client", "user", "1234", &[], 0),
+            );
+        let mut refreshed = token();
+        refreshed.refresh_token(&client).await.unwrap();
+        assert!(refreshed.never_expires());
+        assert_eq!(refreshed.expires_in(), std::time::Duration::MAX);
+        assert!(refreshed.expires_at().is_none());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn from_existing_for_client() {
+        use crate::client::MockClient;
+
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response("other_client", "user", "1234", &[], 3600),
+        );
+        let err = UserToken::from_existing_for_client(
+            &client,
+            AccessToken::from("token"),
+            None,
+            None,
+            &ClientId::from("random_client"),
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, ValidationError::ClientIdMismatch { found, .. } if found.as_str() == "other_client")
+        );
+
+        let token = UserToken::from_existing_for_client(
+            &client,
+            AccessToken::from("token"),
+            None,
+            None,
+            &ClientId::from("other_client"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(token.client_id().as_str(), "other_client");
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn refresh_identity() {
+        use crate::client::MockClient;
+
+        let mut token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .scopes(vec![Scope::ChatRead, Scope::ChatEdit])
+            .build();
+
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response(
+                "random_client",
+                "new_user",
+                "1234",
+                &[Scope::ChatRead],
+                3600,
+            ),
+        );
+        token.refresh_identity(&client).await.unwrap();
+        assert_eq!(token.login.as_str(), "new_user");
+        assert_eq!(token.scopes(), [Scope::ChatRead]);
+
+        token.set_login("renamed".into());
+        assert_eq!(token.login.as_str(), "renamed");
+
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response("random_client", "other", "4321", &[], 3600),
+        );
+        let err = token.refresh_identity(&client).await.unwrap_err();
+        assert!(matches!(err, ValidationError::InvalidToken(_)));
+        assert_eq!(token.login.as_str(), "renamed");
+        assert_eq!(token.scopes(), [Scope::ChatRead]);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn refresh_keeps_new_tokens_when_validation_fails() {
+        use crate::client::MockClient;
+
+        let mut token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .client_secret("secret")
+            .refresh_token("refresh")
+            .scopes(vec![Scope::ChatRead])
+            .expires_in(std::time::Duration::from_secs(10))
+            .build();
+
+        // No expiry or scopes in the response, so the token is validated, but there is no validation response.
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            http::Response::builder()
+                .body(br#"{"access_token":"new_token","refresh_token":"new_refresh"}"#.to_vec())
+                .unwrap(),
+        );
+        assert!(token.refresh_token(&client).await.is_err());
+        assert_eq!(token.access_token.secret(), "new_token");
+        assert_eq!(
+            token.refresh_token.as_ref().unwrap().secret(),
+            "new_refresh"
+        );
+        assert_eq!(token.scopes(), [Scope::ChatRead]);
+        assert!(!token.never_expires());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn refresh_keeps_expiry_when_validation_is_unauthorized() {
+        use crate::client::MockClient;
+
+        let mut token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .client_secret("secret")
+            .refresh_token("refresh")
+            .expires_in(std::time::Duration::from_secs(10))
+            .build();
+        let created_at = token.created_at();
+
+        let client = MockClient::new()
+            .with_response(
+                http::Method::POST,
+                &crate::TOKEN_URL,
+                http::Response::builder()
+                    .body(
+                        br#"{"access_token":"new_token","refresh_token":"new_refresh","scope":[]}"#
+                            .to_vec(),
+                    )
+                    .unwrap(),
+            )
+            .with_response(
+                http::Method::GET,
+                &crate::VALIDATE_URL,
+                MockClient::error_response(http::StatusCode::UNAUTHORIZED, "invalid access token"),
+            );
+        assert!(token.refresh_token(&client).await.is_err());
+        assert_eq!(
+            token.refresh_token.as_ref().unwrap().secret(),
+            "new_refresh"
+        );
+        // The lifetime is not extended, as the new expiry is unknown.
+        assert_eq!(token.created_at(), created_at);
+        assert_eq!(
+            token.initial_expires_in(),
+            Some(std::time::Duration::from_secs(10))
+        );
+        assert!(token.expires_in() <= std::time::Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn refresh_with_outcome() {
+        use crate::client::MockClient;
+
+        let mut token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .client_secret("secret")
+            .refresh_token("refresh")
+            .build();
+
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("new_token", Some("refresh"), &[], 3600),
+        );
+        let outcome = token.refresh_with_outcome(&client).await.unwrap();
+        assert!(!outcome.refresh_token_changed);
+
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("new_token", Some("new_refresh"), &[], 3600),
+        );
+        let outcome = token.refresh_with_outcome(&client).await.unwrap();
+        assert!(outcome.refresh_token_changed);
+        assert_eq!(outcome.refresh_token.unwrap().secret(), "new_refresh");
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn validate_or_refresh() {
+        use crate::client::MockClient;
+
+        let token = || {
+            UserToken::unchecked_builder("token", "random_client", "user", "1234")
+                .refresh_token("refresh")
+                .build()
+        };
+
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response("random_client", "user", "1234", &[], 3600),
+        );
+        let validated = token().validate_or_refresh(&client).await.unwrap();
+        assert_eq!(validated.access_token.secret(), "token");
+
+        let client = MockClient::new()
+            .with_response(
+                http::Method::GET,
+                &crate::VALIDATE_URL,
+                MockClient::error_response(http::StatusCode::UNAUTHORIZED, "invalid access token"),
+            )
+            .with_response(
+                http::Method::POST,
+                &crate::TOKEN_URL,
+                MockClient::token_response("new_token", Some("new_refresh"), &[], 3600),
+            );
+        // No client secret, so the token can not be refreshed
+        let err = token().validate_or_refresh(&client).await.unwrap_err();
+        assert!(matches!(
+            err,
+            ValidateOrRefreshError::Refresh(RefreshTokenError::NoClientSecretFound)
+        ));
+
+        let mut refreshable = token();
+        refreshable.set_secret(Some("secret".into()));
+        // The mocked validation rejects every token, including the refreshed one
+        let err = refreshable.validate_or_refresh(&client).await.unwrap_err();
+        assert!(matches!(
+            err,
+            ValidateOrRefreshError::StillInvalid(ValidationError::NotAuthorized)
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn refresh_implicit_token() {
+        let mut token = UserToken::from_existing_unchecked(
+            AccessToken::from("token"),
+            None,
+            ClientId::from("random_client"),
+            None,
+            UserName::from("user"),
+            UserId::from("1234"),
+            None,
+            None,
+        );
+        let err = token
+            .refresh_token(&crate::client::DummyClient)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RefreshTokenError::NotRefreshable { .. }));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn failed_refresh_keeps_refresh_token() {
+        use crate::client::MockClient;
+
+        let mut token = UserToken::unchecked_builder("token", "random_client", "user", "1234")
+            .client_secret("secret")
+            .refresh_token("refresh")
+            .build();
+
+        // No response is defined, so the request fails
+        let err = token.refresh_token(&MockClient::new()).await.unwrap_err();
+        assert!(matches!(err, RefreshTokenError::RequestError(_)));
+        assert_eq!(token.refresh_token.as_ref().unwrap().secret(), "refresh");
+        assert!(token.can_refresh());
+
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::token_response("new_token", Some("new_refresh"), &[], 3600),
+        );
+        token.refresh_token(&client).await.unwrap();
+        assert_eq!(
+            token.refresh_token.as_ref().unwrap().secret(),
+            "new_refresh"
+        );
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn complete_exchange() {
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.set_csrf(crate::CsrfToken::from("random"));
+        let err = builder
+            .complete_exchange(&crate::client::DummyClient, "random")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UserTokenExchangeError::NoCode));
+
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.set_csrf(crate::CsrfToken::from("random"));
+        builder.prepare_exchange("authcode");
+        let err = builder
+            .complete_exchange(&crate::client::DummyClient, "not_random")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UserTokenExchangeError::StateMismatch));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn state_mismatch() {
+        use crate::client::MockClient;
+
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.set_csrf(crate::CsrfToken::from("random"));
+        let err = builder
+            .get_user_token(&MockClient::new(), "randon", "authcode")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UserTokenExchangeError::StateMismatch));
+
+        let client = MockClient::new().with_response(
+            http::Method::GET,
+            &crate::VALIDATE_URL,
+            MockClient::validate_response("random_client", "justintv", "1337", &[], 3600),
+        );
+        let builder = || {
+            let mut builder = ImplicitUserTokenBuilder::new(
+                ClientId::from("random_client"),
+                url::Url::parse("https://localhost").unwrap(),
+            );
+            builder.csrf = Some(crate::CsrfToken::from("random"));
+            builder
+        };
+        let err = builder()
+            .get_user_token(&client, Some("randon"), Some("token"), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ImplicitUserTokenExchangeError::StateMismatch));
+        let token = builder()
+            .get_user_token(&client, Some("random"), Some("token"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(token.login.as_str(), "justintv");
+
+        let err = builder()
+            .get_user_token(&client, None, Some("token"), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ImplicitUserTokenExchangeError::StateMismatch));
+        let token = builder()
+            .allow_missing_state(true)
+            .get_user_token(&client, None, Some("token"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(token.login.as_str(), "justintv");
+        let err = builder()
+            .allow_missing_state(true)
+            .get_user_token(&client, Some("randon"), Some("token"), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ImplicitUserTokenExchangeError::StateMismatch));
+
+        let mut builder = builder();
+        builder.set_secret(Some(ClientSecret::from("secret")));
+        let token = builder
+            .get_user_token(&client, Some("random"), Some("token"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            token.client_secret.as_ref().map(|s| s.secret()),
+            Some("secret")
+        );
+        assert!(!token.can_refresh());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "client")]
+    async fn get_token_mock() {
+        use crate::client::MockClient;
+
+        let client = MockClient::new()
+            .with_response(
+                http::Method::POST,
+                &crate::TOKEN_URL,
+                MockClient::token_response("token", Some("refresh"), &[Scope::ChatRead], 3600),
+            )
+            .with_response(
+                http::Method::GET,
+                &crate::VALIDATE_URL,
+                MockClient::validate_response(
+                    "random_client",
+                    "justintv",
+                    "1337",
+                    &[Scope::ChatRead],
+                    3600,
+                ),
+            );
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.set_csrf(crate::CsrfToken::from("random"));
+        let token = builder
+            .get_user_token(
+                &client,
+                "random",
+                crate::AuthorizationCode::from("authcode"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(token.access_token.secret(), "token");
+        assert_eq!(token.login.as_str(), "justintv");
+        assert_eq!(token.scopes(), [Scope::ChatRead]);
+
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.set_csrf(crate::CsrfToken::from("random"));
+        let (token, response) = builder
+            .get_user_token_response(&client, "random", "authcode")
+            .await
+            .unwrap();
+        assert_eq!(token.access_token, response.access_token);
+        assert_eq!(response.token_type.as_deref(), Some("bearer"));
+
+        let client = MockClient::new().with_response(
+            http::Method::POST,
+            &crate::TOKEN_URL,
+            MockClient::error_response(http::StatusCode::BAD_REQUEST, "Invalid authorization code"),
+        );
+        let mut builder = UserTokenBuilder::new(
+            ClientId::from("random_client"),
+            ClientSecret::from("random_secret"),
+            url::Url::parse("https://localhost").unwrap(),
+        );
+        builder.set_csrf(crate::CsrfToken::from("random"));
+        let err = builder
+            .get_user_token(&client, "random", "authcode")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            UserTokenExchangeError::RequestParseError(crate::RequestParseError::TwitchError(_))
+        ));
     }
 
     #[tokio::test]
@@ -1,5 +1,8 @@
 #![allow(clippy::extra_unused_lifetimes)]
 //! Types used in OAUTH2 flow.
+//!
+//! These types are defined by this crate and not re-exported from another oauth2 library, so updating dependencies does not change them.
+//! They can be created from a `String` or `&str`, converted back into a `String`, and (de)serialized with [`serde`].
 
 use std::fmt;
 
@@ -9,9 +12,86 @@ use base64::Engine;
 #[aliri_braid::braid(serde)]
 pub struct ClientId;
 
+/// String holding a secret, used inside [`ClientSecret`], [`AccessToken`] and [`RefreshToken`].
+///
+/// With the `zeroize` feature, the memory is overwritten with zeroes when it's dropped, including when a secret is replaced, like when refreshing a token.
+#[derive(
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde_derive::Serialize,
+    serde_derive::Deserialize,
+)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for SecretString {
+    fn zeroize(&mut self) { zeroize::Zeroize::zeroize(&mut self.0) }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for SecretString {}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("[redacted secret]") }
+}
+
+impl std::ops::Deref for SecretString {
+    type Target = str;
+
+    fn deref(&self) -> &str { &self.0 }
+}
+
+impl AsRef<str> for SecretString {
+    fn as_ref(&self) -> &str { &self.0 }
+}
+
+impl std::borrow::Borrow<str> for SecretString {
+    fn borrow(&self) -> &str { &self.0 }
+}
+
+impl std::str::FromStr for SecretString {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Ok(SecretString(s.to_owned())) }
+}
+
+impl From<String> for SecretString {
+    fn from(s: String) -> Self { SecretString(s) }
+}
+
+impl From<&str> for SecretString {
+    fn from(s: &str) -> Self { SecretString(s.to_owned()) }
+}
+
+impl From<Box<str>> for SecretString {
+    fn from(s: Box<str>) -> Self { SecretString(s.into()) }
+}
+
+impl From<SecretString> for String {
+    // Take the string, leaving an empty one to be dropped.
+    fn from(mut s: SecretString) -> Self { std::mem::take(&mut s.0) }
+}
+
+impl From<SecretString> for Box<str> {
+    fn from(s: SecretString) -> Self { String::from(s).into_boxed_str() }
+}
+
 /// A Client Secret
 #[aliri_braid::braid(display = "owned", debug = "owned", serde)]
-pub struct ClientSecret;
+pub struct ClientSecret(SecretString);
 
 impl fmt::Debug for ClientSecretRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -26,7 +106,7 @@ impl fmt::Display for ClientSecretRef {
 
 /// An Access Token
 #[aliri_braid::braid(display = "owned", debug = "owned", serde)]
-pub struct AccessToken;
+pub struct AccessToken(SecretString);
 
 impl fmt::Debug for AccessTokenRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -41,7 +121,7 @@ impl fmt::Display for AccessTokenRef {
 
 /// A Refresh Token
 #[aliri_braid::braid(display = "owned", debug = "owned", serde)]
-pub struct RefreshToken;
+pub struct RefreshToken(SecretString);
 
 impl fmt::Debug for RefreshTokenRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -54,6 +134,21 @@ impl fmt::Display for RefreshTokenRef {
     }
 }
 
+/// An Authorization Code, received on the redirect url in the [authorization code flow](https://dev.twitch.tv/docs/authentication/getting-tokens-oauth/#authorization-code-grant-flow)
+#[aliri_braid::braid(display = "owned", debug = "owned", serde)]
+pub struct AuthorizationCode;
+
+impl fmt::Debug for AuthorizationCodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted authorization code]")
+    }
+}
+impl fmt::Display for AuthorizationCodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted authorization code]")
+    }
+}
+
 /// A Csrf Token
 #[aliri_braid::braid(display = "owned", debug = "owned", serde)]
 pub struct CsrfToken;
@@ -69,6 +164,75 @@ impl fmt::Display for CsrfTokenRef {
     }
 }
 
+/// A PKCE Code Verifier
+#[aliri_braid::braid(display = "owned", debug = "owned", serde)]
+pub struct PkceCodeVerifier;
+
+impl fmt::Debug for PkceCodeVerifierRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted pkce code verifier]")
+    }
+}
+impl fmt::Display for PkceCodeVerifierRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted pkce code verifier]")
+    }
+}
+
+/// The client id and client secret of an application, from [the Twitch Developer Console](https://dev.twitch.tv/console).
+///
+/// Keeps the two together, so they can't be swapped when passed to functions.
+#[derive(Debug, Clone)]
+pub struct ClientCredentials {
+    /// The client id
+    pub client_id: ClientId,
+    /// The client secret
+    pub client_secret: ClientSecret,
+}
+
+impl ClientCredentials {
+    /// Create [`ClientCredentials`]
+    pub fn new(
+        client_id: impl Into<ClientId>,
+        client_secret: impl Into<ClientSecret>,
+    ) -> ClientCredentials {
+        ClientCredentials {
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+        }
+    }
+
+    /// Read [`ClientCredentials`] from the `TWITCH_CLIENT_ID` and `TWITCH_CLIENT_SECRET` environment variables.
+    pub fn from_env() -> Result<ClientCredentials, crate::tokens::errors::FromEnvError> {
+        let var = |name: &'static str| {
+            std::env::var(name)
+                .map_err(|source| crate::tokens::errors::FromEnvError::Var { name, source })
+        };
+        Ok(ClientCredentials::new(
+            var("TWITCH_CLIENT_ID")?,
+            var("TWITCH_CLIENT_SECRET")?,
+        ))
+    }
+}
+
+/// Overwrites the secret with zeroes, leaving an empty secret in its place.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for ClientSecret {
+    fn zeroize(&mut self) { zeroize::Zeroize::zeroize(&mut self.0) }
+}
+
+/// Overwrites the secret with zeroes, leaving an empty secret in its place.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for AccessToken {
+    fn zeroize(&mut self) { zeroize::Zeroize::zeroize(&mut self.0) }
+}
+
+/// Overwrites the secret with zeroes, leaving an empty secret in its place.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for RefreshToken {
+    fn zeroize(&mut self) { zeroize::Zeroize::zeroize(&mut self.0) }
+}
+
 impl CsrfToken {
     /// Make a new random CSRF token.
     pub fn new_random() -> CsrfToken { Self::new_random_len(16) }
@@ -81,6 +245,29 @@ impl CsrfToken {
     }
 }
 
+impl PkceCodeVerifier {
+    /// Make a new random PKCE code verifier.
+    pub fn new_random() -> PkceCodeVerifier { Self::new_random_len(32) }
+
+    /// Make a new random PKCE code verifier with given amount of bytes.
+    ///
+    /// Per [RFC 7636](https://datatracker.ietf.org/doc/html/rfc7636#section-4.1) the resulting verifier must be between 43 and 128 characters, i.e `len` should be between 32 and 96.
+    pub fn new_random_len(len: u32) -> PkceCodeVerifier {
+        use rand::Rng as _;
+        let random_bytes: Vec<u8> = (0..len).map(|_| rand::thread_rng().gen::<u8>()).collect();
+        PkceCodeVerifier::new(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(random_bytes))
+    }
+}
+
+impl PkceCodeVerifierRef {
+    /// Get the `S256` code challenge for this verifier.
+    pub fn code_challenge(&self) -> String {
+        use sha2::Digest as _;
+        let digest = sha2::Sha256::digest(self.secret().as_bytes());
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(digest)
+    }
+}
+
 impl ClientSecretRef {
     /// Get the secret from this string.
     ///
@@ -100,9 +287,84 @@ impl RefreshTokenRef {
     /// This function is the same as [`RefreshToken::as_str`](RefreshTokenRef::as_str), but has another name for searchability, prefer to use this function.
     pub fn secret(&self) -> &str { self.as_str() }
 }
+impl AuthorizationCodeRef {
+    /// Get the secret from this string.
+    ///
+    /// This function is the same as [`AuthorizationCode::as_str`](AuthorizationCodeRef::as_str), but has another name for searchability, prefer to use this function.
+    pub fn secret(&self) -> &str { self.as_str() }
+}
 impl CsrfTokenRef {
     /// Get the secret from this string.
     ///
     /// This function is the same as [`CsrfToken::as_str`](CsrfTokenRef::as_str), but has another name for searchability, prefer to use this function.
     pub fn secret(&self) -> &str { self.as_str() }
+
+    /// Compare the secret with `other` in constant time, to not leak timing information.
+    ///
+    /// Only the length of the secret can be inferred from timing.
+    pub fn secret_eq(&self, other: &str) -> bool {
+        let (a, b) = (self.secret().as_bytes(), other.as_bytes());
+        a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+}
+impl PkceCodeVerifierRef {
+    /// Get the secret from this string.
+    ///
+    /// This function is the same as [`PkceCodeVerifier::as_str`](PkceCodeVerifierRef::as_str), but has another name for searchability, prefer to use this function.
+    pub fn secret(&self) -> &str { self.as_str() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkce_code_challenge() {
+        // https://datatracker.ietf.org/doc/html/rfc7636#appendix-B
+        let verifier = PkceCodeVerifier::from("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+        assert_eq!(
+            verifier.code_challenge(),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert_eq!(PkceCodeVerifier::new_random().as_str().len(), 43);
+    }
+
+    #[test]
+    fn secret_string() {
+        let token = AccessToken::from("token");
+        assert_eq!(token.secret(), "token");
+        let string: String = token.into();
+        assert_eq!(string, "token");
+        assert_eq!(
+            format!("{:?}", SecretString::from("secret")),
+            "[redacted secret]"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize_secret() {
+        use zeroize::Zeroize;
+
+        let mut token = RefreshToken::from("refresh");
+        token.zeroize();
+        assert_eq!(token.secret(), "");
+    }
+
+    #[test]
+    fn csrf_secret_eq() {
+        let csrf = CsrfToken::from("random");
+        assert!(csrf.secret_eq("random"));
+        assert!(!csrf.secret_eq("randon"));
+        assert!(!csrf.secret_eq("random2"));
+        assert!(!csrf.secret_eq(""));
+    }
+
+    #[test]
+    fn client_credentials() {
+        let credentials = ClientCredentials::new("client_id", "client_secret");
+        assert_eq!(credentials.client_id.as_str(), "client_id");
+        assert_eq!(credentials.client_secret.secret(), "client_secret");
+        assert!(!format!("{credentials:?}").contains("client_secret\""));
+    }
 }